@@ -0,0 +1,43 @@
+use core::time::Duration;
+use rand::{rngs::StdRng, Rng};
+use strum_macros::EnumIter;
+
+const MIN_EVENT_INTERVAL: Duration = Duration::from_secs(25);
+const MAX_EVENT_INTERVAL: Duration = Duration::from_secs(35);
+const EVENT_DURATION: Duration = Duration::from_secs(10);
+
+// Instant events only keep their banner up long enough to be read
+const BANNER_DURATION: Duration = Duration::from_secs(3);
+
+pub const SPEED_BURST_FACTOR: f32 = 4.0;
+pub const BONUS_GARBAGE_ROWS: u32 = 3;
+
+#[derive(Clone, Copy, EnumIter, PartialEq)]
+pub enum ChaosEvent {
+    MirroredControls,
+    InvisibleStack,
+    SpeedBurst,
+    BonusGarbage,
+}
+
+impl ChaosEvent {
+    pub fn name(&self) -> &str {
+        match self {
+            ChaosEvent::MirroredControls => "MIRRORED CONTROLS",
+            ChaosEvent::InvisibleStack => "INVISIBLE STACK",
+            ChaosEvent::SpeedBurst => "SPEED BURST",
+            ChaosEvent::BonusGarbage => "BONUS GARBAGE",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            ChaosEvent::BonusGarbage => BANNER_DURATION,
+            _ => EVENT_DURATION,
+        }
+    }
+}
+
+pub fn event_interval(rng: &mut StdRng) -> Duration {
+    rng.gen_range(MIN_EVENT_INTERVAL..=MAX_EVENT_INTERVAL)
+}
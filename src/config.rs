@@ -1,110 +1,683 @@
-use ini::Ini;
+use std::{io::{ErrorKind, Result}, str::FromStr, sync::{RwLock, RwLockReadGuard, RwLockWriteGuard}, time::Duration};
+use crossterm::style::{Color, ContentStyle};
+use ini::{Error, Ini};
 use home::home_dir;
 use lazy_static::lazy_static;
 
+use crate::{display::{BlockStyle, LineClearAnimation}, game::GarbagePattern, gravity::SpeedCurve, rotation::{rotation_system, RotationSystem}, theme::{self, parse_hex_color, Theme}};
+
 lazy_static! {
     static ref CONFIG_PATH: String = format!("{}/.config/tetris.ini", home_dir().unwrap().to_str().unwrap());
-    static ref CONFIG: Ini = Ini::load_from_file(&*CONFIG_PATH).unwrap_or(Ini::new());
+    static ref CONFIG: Ini = Ini::load_from_file(&*CONFIG_PATH).unwrap_or_default();
+
+    static ref SETTINGS: RwLock<Settings> = RwLock::new(Settings {
+        show_ghost: CONFIG
+            .get_from_or(Some("display"), "show_ghost", "true")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid show_ghost display config value")),
+        display_frame_rate: CONFIG
+            .get_from_or(Some("display"), "display_frame_rate", "false")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid display_frame_rate display config value")),
+        show_stats: CONFIG
+            .get_from_or(Some("display"), "show_stats", "false")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid show_stats display config value")),
+        colorblind: CONFIG
+            .get_from_or(Some("display"), "colorblind", "false")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid colorblind display config value")),
+        show_hint: CONFIG
+            .get_from_or(Some("display"), "show_hint", "false")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid show_hint display config value")),
+        line_clear_animation: CONFIG
+            .get_from_or(Some("display"), "line_clear_animation", "flash")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid line_clear_animation display config value")),
+        use_xterm_256_colors: CONFIG
+            .get_from_or(Some("display"), "use_xterm_256_colors", "true")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid use_xterm_256_colors display config value")),
+        das: Duration::from_millis(CONFIG
+            .get_from_or(Some("gameplay"), "das", "167")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid das gameplay config value"))),
+        arr: Duration::from_millis(CONFIG
+            .get_from_or(Some("gameplay"), "arr", "33")
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid arr gameplay config value"))),
+    });
 
     pub static ref MAX_FRAME_RATE: u64 = CONFIG
         .get_from_or(Some("display"), "max_frame_rate", "60")
         .parse()
         .unwrap_or_else(|_| panic!("Invalid max_frame_rate display config value"));
 
-    pub static ref DISPLAY_FRAME_RATE: bool = CONFIG
-        .get_from_or(Some("display"), "display_frame_rate", "false")
+    pub static ref HALF_BLOCK: bool = CONFIG
+        .get_from_or(Some("display"), "half_block", "false")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid half_block display config value"));
+
+    pub static ref SCREEN_SHAKE: bool = CONFIG
+        .get_from_or(Some("display"), "screen_shake", "false")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid screen_shake display config value"));
+
+    pub static ref PARTICLES: bool = CONFIG
+        .get_from_or(Some("display"), "particles", "false")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid particles display config value"));
+
+    pub static ref HIGH_CONTRAST: bool = CONFIG
+        .get_from_or(Some("display"), "high_contrast", "false")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid high_contrast display config value"));
+
+    static ref THEME: RwLock<Theme> = RwLock::new(load_theme());
+
+    pub static ref BLOCK_STYLE: BlockStyle = CONFIG
+        .get_from_or(Some("display"), "block_style", "solid")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid block_style display config value"));
+
+    pub static ref ENTRY_DELAY: Duration = Duration::from_millis(CONFIG
+        .get_from_or(Some("gameplay"), "entry_delay", "0")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid entry_delay gameplay config value")));
+
+    pub static ref SPEED_CURVE: SpeedCurve = CONFIG
+        .get_from_or(Some("gameplay"), "speed_curve", "guideline")
         .parse()
-        .unwrap_or_else(|_| panic!("Invalid display_frame_rate display config value"));
+        .unwrap_or_else(|_| panic!("Invalid speed_curve gameplay config value"));
 
-    pub static ref USE_XTERM_256_COLORS: bool = CONFIG
-        .get_from_or(Some("display"), "use_xterm_256_colors", "true")
+    pub static ref BOARD_WIDTH: i32 = CONFIG
+        .get_from_or(Some("gameplay"), "board_width", "10")
         .parse()
-        .unwrap_or_else(|_| panic!("Invalid use_xterm_256_colors display config value"));
+        .unwrap_or_else(|_| panic!("Invalid board_width gameplay config value"));
+
+    pub static ref BOARD_HEIGHT: i32 = CONFIG
+        .get_from_or(Some("gameplay"), "board_height", "20")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid board_height gameplay config value"));
+
+    pub static ref PLAYER_NAME: Option<String> = CONFIG
+        .get_from(Some("player"), "name")
+        .map(|name| name.trim().trim_matches('"').to_string())
+        .filter(|name| !name.is_empty());
+
+    pub static ref MAX_LEVEL: Option<u32> = CONFIG
+        .get_from(Some("gameplay"), "max_level")
+        .map(|max_level| max_level
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid max_level gameplay config value")));
+
+    pub static ref GRAVITY_TABLE: Option<Vec<f32>> = CONFIG
+        .get_from(Some("gameplay"), "gravity_table")
+        .map(|gravity_table| gravity_table
+            .split(',')
+            .map(|frames| frames
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid gravity_table gameplay config value")))
+            .collect());
+
+    pub static ref SOFT_DROP_FACTOR: f32 = match CONFIG.get_from_or(Some("gameplay"), "soft_drop_factor", "20") {
+        "instant" => f32::INFINITY,
+        factor => factor
+            .parse()
+            .ok()
+            .filter(|factor| *factor > 0.0)
+            .unwrap_or_else(|| panic!("Invalid soft_drop_factor gameplay config value")),
+    };
+
+    pub static ref ALL_SPIN: bool = CONFIG
+        .get_from_or(Some("gameplay"), "all_spin", "false")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid all_spin gameplay config value"));
+
+    pub static ref MARATHON_LINES: u32 = CONFIG
+        .get_from_or(Some("gameplay"), "marathon_lines", "150")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid marathon_lines gameplay config value"));
+
+    pub static ref CHEESE_ROWS: u32 = CONFIG
+        .get_from_or(Some("gameplay"), "cheese_rows", "10")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid cheese_rows gameplay config value"));
+
+    pub static ref TRIAL_LEVEL: u32 = CONFIG
+        .get_from_or(Some("gameplay"), "trial_level", "10")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid trial_level gameplay config value"));
+
+    pub static ref GARBAGE_INTERVAL: Duration = Duration::from_millis(CONFIG
+        .get_from_or(Some("gameplay"), "garbage_interval", "5000")
+        .parse()
+        .ok()
+        .filter(|interval| *interval > 0)
+        .unwrap_or_else(|| panic!("Invalid garbage_interval gameplay config value")));
+
+    pub static ref GARBAGE_LINES: u32 = CONFIG
+        .get_from_or(Some("gameplay"), "garbage_lines", "1")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid garbage_lines gameplay config value"));
+
+    pub static ref GARBAGE_PATTERN: GarbagePattern = CONFIG
+        .get_from_or(Some("gameplay"), "garbage_pattern", "messy")
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid garbage_pattern gameplay config value"));
+
+    pub static ref ROTATION_SYSTEM: Box<dyn RotationSystem> = rotation_system(CONFIG
+        .get_from_or(Some("gameplay"), "rotation_system", "srs"))
+        .unwrap_or_else(|| panic!("Invalid rotation_system gameplay config value"));
+}
+
+fn load_theme() -> Theme {
+    let mut theme = theme::theme(CONFIG
+        .get_from_or(Some("display"), "theme", "guideline"))
+        .unwrap_or_else(|| panic!("Invalid theme display config value"));
+
+    // Exact colors given as color_I through color_Z replace the theme's piece colors
+    for (i, variant) in "IJLOSTZ".chars().enumerate() {
+        if let Some(color) = CONFIG.get_from(Some("display"), &format!("color_{}", variant)) {
+            theme.pieces[i] = parse_hex_color(color)
+                .unwrap_or_else(|| panic!("Invalid color_{} display config value", variant));
+        }
+    }
+
+    // Pieces keep their colors to be told apart by pattern, but nothing else is colored
+    if *HIGH_CONTRAST {
+        theme.border = Color::Reset;
+        theme.background = Color::Reset;
+        theme.text = ContentStyle::new();
+    }
+
+    theme
+}
+
+pub fn theme() -> RwLockReadGuard<'static, Theme> {
+    THEME.read().unwrap()
+}
+
+// Rebuilds the theme after a setting it depends on changed
+pub fn reload_theme() {
+    *THEME.write().unwrap() = load_theme();
+}
+
+// Options that can be changed from the settings menu while the game is running
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub show_ghost: bool,
+    pub display_frame_rate: bool,
+    pub show_stats: bool,
+    pub colorblind: bool,
+    pub show_hint: bool,
+    pub line_clear_animation: LineClearAnimation,
+    pub use_xterm_256_colors: bool,
+    pub das: Duration,
+    pub arr: Duration,
+}
+
+pub fn settings() -> RwLockReadGuard<'static, Settings> {
+    SETTINGS.read().unwrap()
+}
+
+pub fn settings_mut() -> RwLockWriteGuard<'static, Settings> {
+    SETTINGS.write().unwrap()
+}
+
+// Other options are written back as they were loaded
+pub fn save_settings() -> Result<()> {
+    let settings = *settings();
+    let mut config = CONFIG.clone();
+    config
+        .with_section(Some("display"))
+        .set("show_ghost", settings.show_ghost.to_string())
+        .set("display_frame_rate", settings.display_frame_rate.to_string())
+        .set("show_stats", settings.show_stats.to_string())
+        .set("colorblind", settings.colorblind.to_string())
+        .set("show_hint", settings.show_hint.to_string())
+        .set("line_clear_animation", settings.line_clear_animation.to_string())
+        .set("use_xterm_256_colors", settings.use_xterm_256_colors.to_string());
+    config
+        .with_section(Some("gameplay"))
+        .set("das", settings.das.as_millis().to_string())
+        .set("arr", settings.arr.as_millis().to_string());
+    controls::save(&mut config);
+    config.write_to_file(&*CONFIG_PATH)
+}
+
+// Keys that aren't set fall back to valid defaults
+fn check(errors: &mut Vec<String>, section: &str, key: &str, expected: &str, valid: impl Fn(&str) -> bool) {
+    if let Some(value) = CONFIG.get_from(Some(section), key) {
+        if !valid(value) {
+            errors.push(format!("[{}] {} = {}: expected {}", section, key, value, expected));
+        }
+    }
+}
+
+fn parses<T: FromStr>(value: &str) -> bool {
+    value.parse::<T>().is_ok()
+}
+
+// Options are otherwise only checked when first read, which panics
+pub fn validate() -> Vec<String> {
+    let mut errors = Vec::new();
+
+    // A config file that doesn't exist is the same as an empty one
+    match Ini::load_from_file(&*CONFIG_PATH) {
+        Err(Error::Io(err)) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => errors.push(format!("{}: {}", *CONFIG_PATH, err)),
+        Ok(_) => (),
+    }
+
+    for key in [
+        "show_ghost",
+        "display_frame_rate",
+        "show_stats",
+        "colorblind",
+        "show_hint",
+        "use_xterm_256_colors",
+        "half_block",
+        "screen_shake",
+        "particles",
+        "high_contrast",
+    ] {
+        check(&mut errors, "display", key, "true or false", parses::<bool>);
+    }
+    check(&mut errors, "display", "line_clear_animation", "flash, sweep, dissolve, or collapse", parses::<LineClearAnimation>);
+    check(&mut errors, "display", "max_frame_rate", "a whole number", parses::<u64>);
+    check(&mut errors, "display", "theme", "guideline, pastel, or monochrome", |value| theme::theme(value).is_some());
+    for variant in "IJLOSTZ".chars() {
+        check(&mut errors, "display", &format!("color_{}", variant), "a color such as #00ffff", |value| parse_hex_color(value).is_some());
+    }
+    check(&mut errors, "display", "block_style", "solid, shaded, bracketed, or ascii", parses::<BlockStyle>);
+
+    for key in ["entry_delay", "das", "arr"] {
+        check(&mut errors, "gameplay", key, "a number of milliseconds", parses::<u64>);
+    }
+    check(&mut errors, "gameplay", "speed_curve", "guideline, master, 20g, or nes", parses::<SpeedCurve>);
+    for key in ["board_width", "board_height"] {
+        check(&mut errors, "gameplay", key, "a number of cells of at least 4", |value| {
+            value.parse::<i32>().is_ok_and(|cells| cells >= 4)
+        });
+    }
+    for key in ["marathon_lines", "cheese_rows", "trial_level", "garbage_lines"] {
+        check(&mut errors, "gameplay", key, "a whole number", parses::<u32>);
+    }
+    check(&mut errors, "gameplay", "max_level", "a whole number of at least 1", |value| {
+        value.parse::<u32>().is_ok_and(|level| level >= 1)
+    });
+    check(&mut errors, "gameplay", "gravity_table", "a comma separated list of frames per row above 0", |value| {
+        value.split(',').all(|frames| frames.trim().parse::<f32>().is_ok_and(|frames| frames > 0.0 && frames.is_finite()))
+    });
+    check(&mut errors, "gameplay", "soft_drop_factor", "a number above 0 or instant", |value| {
+        value == "instant" || value.parse::<f32>().is_ok_and(|factor| factor > 0.0)
+    });
+    check(&mut errors, "gameplay", "all_spin", "true or false", parses::<bool>);
+    check(&mut errors, "gameplay", "garbage_interval", "a number of milliseconds above 0", |value| {
+        value.parse::<u64>().is_ok_and(|interval| interval > 0)
+    });
+    check(&mut errors, "gameplay", "garbage_pattern", "clean, messy, or 4wide", parses::<GarbagePattern>);
+    check(&mut errors, "gameplay", "rotation_system", "srs, simple, or classic", |value| rotation_system(value).is_some());
+
+    controls::validate(&mut errors);
+
+    errors
 }
 
 pub mod controls {
 
-    use std::collections::HashMap;
-    use crossterm::event::KeyCode;
+    use std::{collections::HashMap, sync::RwLock};
+    use crossterm::event::{KeyCode, KeyEvent, KeyEventState, KeyModifiers};
+    use ini::Ini;
     use lazy_static::lazy_static;
+    use strum::IntoEnumIterator;
 
-    use crate::{config::CONFIG, event::Action};
+    use crate::{config::{check, CONFIG}, event::Action};
 
-    fn key_map(key: &str, action: Action) -> HashMap<KeyCode, Action> {
-        let mut action_map = HashMap::new();
-        let key = key.trim();
-        if key.len() == 1 && key.is_ascii() {
-            let char = key.chars().next().unwrap();
-            action_map.insert(KeyCode::Char(char.to_ascii_uppercase()), action.clone());
-            action_map.insert(KeyCode::Char(char.to_ascii_lowercase()), action);
-        } else {
-            match key {
-                "up"        => action_map.insert(KeyCode::Up, action),
-                "down"      => action_map.insert(KeyCode::Down, action),
-                "left"      => action_map.insert(KeyCode::Left, action),
-                "right"     => action_map.insert(KeyCode::Right, action),
-                "space"     => action_map.insert(KeyCode::Char(' '), action),
-                "escape"    => action_map.insert(KeyCode::Esc, action),
-                _           => panic!("Invalid controls config key value: {}", key),
+    // Letters are always lowercase, with shift held for uppercase ones
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Key {
+        pub code: KeyCode,
+        pub modifiers: KeyModifiers,
+        // Whether the key is on the numeric keypad, which only terminals supporting the kitty
+        // keyboard protocol report
+        pub keypad: bool,
+    }
+
+    impl Key {
+        fn new(code: KeyCode, modifiers: KeyModifiers, keypad: bool) -> Self {
+            let modifiers = modifiers & (KeyModifiers::SHIFT | KeyModifiers::CONTROL | KeyModifiers::ALT);
+            match code {
+                KeyCode::Char(char) if char.is_ascii_uppercase() => {
+                    Key { code: KeyCode::Char(char.to_ascii_lowercase()), modifiers: modifiers | KeyModifiers::SHIFT, keypad }
+                },
+                KeyCode::BackTab => Key { code: KeyCode::Tab, modifiers: modifiers | KeyModifiers::SHIFT, keypad },
+                _ => Key { code, modifiers, keypad },
+            }
+        }
+
+        // Keys looked up in turn until one is bound, so that a key bound without shift or as a
+        // regular key still works when pressed with shift or on the keypad
+        fn fallbacks(&self) -> [Key; 3] {
+            let regular = Key { keypad: false, ..*self };
+            [*self, regular, Key { modifiers: regular.modifiers - KeyModifiers::SHIFT, ..regular }]
+        }
+    }
+
+    impl From<&KeyEvent> for Key {
+        fn from(event: &KeyEvent) -> Self {
+            Key::new(event.code, event.modifiers, event.state.contains(KeyEventState::KEYPAD))
+        }
+    }
+
+    pub type Bindings = Vec<(Action, String)>;
+
+    // Config key of each action along with its default keys
+    pub fn config_key(action: &Action) -> (&'static str, &'static str) {
+        match action {
+            Action::MoveRight   => ("move_right", "right"),
+            Action::MoveLeft    => ("move_left", "left"),
+            Action::RotateRight => ("rotate_right", "up"),
+            Action::RotateLeft  => ("rotate_left", "z"),
+            Action::Rotate180   => ("rotate_180", "x"),
+            Action::SoftDrop    => ("soft_drop", "up"),
+            Action::SonicDrop   => ("sonic_drop", "v"),
+            Action::HardDrop    => ("hard_drop", "space"),
+            Action::Hold        => ("hold", "c"),
+            Action::Hint        => ("hint", "h"),
+            Action::Pause       => ("pause", "p"),
+            Action::Quit        => ("quit", "escape"),
+        }
+    }
+
+    // Keys bound by default to the actions of the second player in co-op, who can't pause or quit
+    fn coop_default(action: &Action) -> Option<&'static str> {
+        match action {
+            Action::MoveRight   => Some("d"),
+            Action::MoveLeft    => Some("a"),
+            Action::RotateRight => Some("w"),
+            Action::RotateLeft  => Some("q"),
+            Action::Rotate180   => Some("e"),
+            Action::SoftDrop    => Some("s"),
+            Action::SonicDrop   => Some("r"),
+            Action::HardDrop    => Some("f"),
+            Action::Hold        => Some("g"),
+            Action::Hint | Action::Pause | Action::Quit => None,
+        }
+    }
+
+    // Named keys other than function keys, where the first name is the one written back to the config
+    const KEY_NAMES: [(&str, KeyCode); 22] = [
+        ("up",          KeyCode::Up),
+        ("down",        KeyCode::Down),
+        ("left",        KeyCode::Left),
+        ("right",       KeyCode::Right),
+        ("space",       KeyCode::Char(' ')),
+        ("escape",      KeyCode::Esc),
+        ("esc",         KeyCode::Esc),
+        ("enter",       KeyCode::Enter),
+        ("return",      KeyCode::Enter),
+        ("tab",         KeyCode::Tab),
+        ("backspace",   KeyCode::Backspace),
+        ("delete",      KeyCode::Delete),
+        ("del",         KeyCode::Delete),
+        ("insert",      KeyCode::Insert),
+        ("ins",         KeyCode::Insert),
+        ("home",        KeyCode::Home),
+        ("end",         KeyCode::End),
+        ("pageup",      KeyCode::PageUp),
+        ("pagedown",    KeyCode::PageDown),
+        // Used as separators in the config file
+        ("comma",       KeyCode::Char(',')),
+        ("plus",        KeyCode::Char('+')),
+        ("menu",        KeyCode::Menu),
+    ];
+
+    // Keys are written like `shift+z`, `ctrl+f1` or `numpad8`
+    pub fn parse_key(name: &str) -> Option<Key> {
+        let name = name.trim().to_lowercase();
+        let mut parts: Vec<&str> = name.split('+').map(str::trim).collect();
+        let key = parts.pop()?;
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier {
+                "shift"             => KeyModifiers::SHIFT,
+                "ctrl" | "control"  => KeyModifiers::CONTROL,
+                "alt"               => KeyModifiers::ALT,
+                _                   => return None,
             };
         }
+
+        let (key, keypad) = match key.strip_prefix("numpad") {
+            Some(key) => (key, true),
+            None => (key, false),
+        };
+
+        let code = if let Some((_, code)) = KEY_NAMES.iter().find(|(name, _)| *name == key) {
+            *code
+        } else if let Some(number) = key.strip_prefix('f').filter(|number| !number.is_empty()) {
+            KeyCode::F(number.parse().ok().filter(|number| (1..=24).contains(number))?)
+        } else if key.chars().count() == 1 && key.is_ascii() {
+            KeyCode::Char(key.chars().next().unwrap())
+        } else {
+            return None
+        };
+
+        Some(Key::new(code, modifiers, keypad))
+    }
+
+    pub fn key_name(key: Key) -> Option<String> {
+        let name = match key.code {
+            KeyCode::F(number) => format!("f{}", number),
+            KeyCode::Char(char) if char.is_ascii_graphic() && char != ',' && char != '+' => char.to_string(),
+            code => String::from(KEY_NAMES.iter().find(|(_, named)| *named == code)?.0),
+        };
+        let mut prefix = String::new();
+        for (modifier, modifier_name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if key.modifiers.contains(modifier) {
+                prefix.push_str(modifier_name);
+            }
+        }
+        Some(format!("{}{}{}", prefix, if key.keypad { "numpad" } else { "" }, name))
+    }
+
+    // Later actions take over keys bound to more than one action
+    fn action_map(bindings: &[(Action, String)]) -> HashMap<Key, Action> {
+        let mut action_map = HashMap::new();
+        for (action, keys) in bindings {
+            for key in keys.split(',') {
+                let key = parse_key(key).unwrap_or_else(|| panic!("Invalid controls config key value: {}", key.trim()));
+                action_map.insert(key, action.clone());
+            }
+        }
         action_map
     }
 
+    // Section of the config file holding a profile, where the default profile is kept under
+    // `[controls]` unless it has a section of its own
+    fn profile_section(name: &str) -> String {
+        if name == DEFAULT_PROFILE && CONFIG.section(Some("controls.default")).is_none() {
+            String::from("controls")
+        } else {
+            format!("controls.{}", name)
+        }
+    }
+
+    // Keys missing from a profile are taken from `[controls]`, and then from the default controls
+    fn load_bindings(name: &str) -> Bindings {
+        let section = profile_section(name);
+        Action::iter()
+            .map(|action| {
+                let (key, default) = config_key(&action);
+                let keys = CONFIG
+                    .get_from(Some(section.as_str()), key)
+                    .or_else(|| CONFIG.get_from(Some("controls"), key))
+                    .unwrap_or(default)
+                    .to_string();
+                (action, keys)
+            })
+            .collect()
+    }
+
+    pub const DEFAULT_PROFILE: &str = "default";
+
+    pub fn validate(errors: &mut Vec<String>) {
+        let sections: Vec<&str> = CONFIG
+            .sections()
+            .flatten()
+            .filter(|section| *section == "controls" || section.starts_with("controls."))
+            .collect();
+        for section in &sections {
+            for action in Action::iter() {
+                let key = config_key(&action).0;
+                check(errors, section, key, "a comma separated list of keys", |keys| {
+                    keys.split(',').all(|key| parse_key(key).is_some())
+                });
+            }
+        }
+        for action in Action::iter().filter(|action| coop_default(action).is_some()) {
+            check(errors, "coop_controls", config_key(&action).0, "a comma separated list of keys", |keys| {
+                keys.split(',').all(|key| parse_key(key).is_some())
+            });
+        }
+        check(errors, "controls", "profile", "default or the name of a [controls.<name>] section", |profile| {
+            profile.trim() == DEFAULT_PROFILE || sections.contains(&format!("controls.{}", profile.trim()).as_str())
+        });
+    }
+
     lazy_static! {
-        pub static ref ACTION_MAP: HashMap<KeyCode, Action> = {
-            let mut action_map = HashMap::new();
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "move_right", "right")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::MoveRight)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "move_left", "left")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::MoveLeft)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "rotate_right", "up")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::RotateRight)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "rotate_left", "z")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::RotateLeft)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "soft_drop", "up")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::SoftDrop)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "hard_drop", "space")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::HardDrop)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "hold", "c")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::Hold)));
-
-            action_map.extend(CONFIG
-                .get_from_or(Some("controls"), "quit", "escape")
-                .to_string()
-                .split(',')
-                .flat_map(|key| key_map(key, Action::Quit)));
-
-            action_map
+        // Name of every control profile along with its bindings
+        static ref PROFILES: RwLock<Vec<(String, Bindings)>> = {
+            let mut names = vec![String::from(DEFAULT_PROFILE)];
+            names.extend(CONFIG
+                .sections()
+                .flatten()
+                .filter_map(|section| section.strip_prefix("controls."))
+                .filter(|name| *name != DEFAULT_PROFILE)
+                .map(String::from));
+            RwLock::new(names.into_iter().map(|name| {
+                let bindings = load_bindings(&name);
+                (name, bindings)
+            }).collect())
+        };
+
+        static ref PROFILE: RwLock<usize> = {
+            let name = CONFIG.get_from_or(Some("controls"), "profile", DEFAULT_PROFILE);
+            RwLock::new(PROFILES
+                .read()
+                .unwrap()
+                .iter()
+                .position(|(profile, _)| profile == name.trim())
+                .unwrap_or_else(|| panic!("Invalid profile controls config value")))
         };
+
+        static ref ACTION_MAP: RwLock<HashMap<Key, Action>> = RwLock::new(action_map(&bindings()));
+
+        static ref COOP_ACTION_MAP: HashMap<Key, Action> = action_map(&Action::iter()
+            .filter_map(|action| {
+                let default = coop_default(&action)?;
+                let keys = CONFIG.get_from_or(Some("coop_controls"), config_key(&action).0, default).to_string();
+                Some((action, keys))
+            })
+            .collect::<Bindings>());
+    }
+
+    pub fn action(key: Key) -> Option<Action> {
+        let action_map = ACTION_MAP.read().unwrap();
+        key.fallbacks().iter().find_map(|key| action_map.get(key)).cloned()
+    }
+
+    pub fn coop_action(key: Key) -> Option<Action> {
+        key.fallbacks().iter().find_map(|key| COOP_ACTION_MAP.get(key)).cloned()
+    }
+
+    // Doesn't fall back to the key without shift or off the keypad
+    pub fn bound_action(key: Key) -> Option<Action> {
+        ACTION_MAP.read().unwrap().get(&key).cloned()
     }
-}
 
+    pub fn bindings() -> Bindings {
+        PROFILES.read().unwrap()[*PROFILE.read().unwrap()].1.clone()
+    }
+
+    pub fn profile() -> String {
+        PROFILES.read().unwrap()[*PROFILE.read().unwrap()].0.clone()
+    }
+
+    pub fn set_profile(name: &str) -> bool {
+        let Some(index) = PROFILES.read().unwrap().iter().position(|(profile, _)| profile == name) else {
+            return false
+        };
+        *PROFILE.write().unwrap() = index;
+        *ACTION_MAP.write().unwrap() = action_map(&bindings());
+        true
+    }
+
+    pub fn cycle_profile(forward: bool) {
+        let count = PROFILES.read().unwrap().len();
+        let index = *PROFILE.read().unwrap();
+        let index = if forward { (index + 1) % count } else { (index + count - 1) % count };
+        let name = PROFILES.read().unwrap()[index].0.clone();
+        set_profile(&name);
+    }
+
+    // Kept in memory until the config is saved
+    pub fn rebind(action: &Action, key: Key) {
+        let Some(name) = key_name(key) else {
+            return
+        };
+        let index = *PROFILE.read().unwrap();
+        if let Some((_, keys)) = PROFILES.write().unwrap()[index].1.iter_mut().find(|(bound, _)| bound == action) {
+            *keys = name;
+        }
+        *ACTION_MAP.write().unwrap() = action_map(&bindings());
+    }
+
+    pub fn save(config: &mut Ini) {
+        config.with_section(Some("controls")).set("profile", profile());
+        for (name, bindings) in PROFILES.read().unwrap().iter() {
+            let section = profile_section(name);
+            for (action, keys) in bindings {
+                config.with_section(Some(section.as_str())).set(config_key(action).0, keys.clone());
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn key_names_round_trip() {
+            for name in ["z", "space", "up", "f12", "comma", "shift+z", "ctrl+alt+f1", "numpad8", "numpadenter"] {
+                let key = parse_key(name).unwrap();
+                assert_eq!(key_name(key).as_deref(), Some(name));
+                assert!(parse_key(&key_name(key).unwrap()) == Some(key));
+            }
+        }
+
+        #[test]
+        fn parses_key_aliases() {
+            assert!(parse_key("Z") == parse_key("z"));
+            assert!(parse_key(" Esc ") == parse_key("escape"));
+            assert!(parse_key("control+x") == parse_key("ctrl+x"));
+            assert_eq!(parse_key("Z").and_then(key_name).as_deref(), Some("z"));
+        }
+
+        #[test]
+        fn rejects_invalid_keys() {
+            for name in ["", "f0", "f25", "hyper+z", "zz", "numpad"] {
+                assert!(parse_key(name).is_none(), "{}", name);
+            }
+            assert_eq!(key_name(Key::new(KeyCode::Null, KeyModifiers::NONE, false)), None);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fs::OpenOptions, io::{Result, Write}, time::{SystemTime, UNIX_EPOCH}};
+use home::home_dir;
+use lazy_static::lazy_static;
+
+use crate::{display::format_duration, game::Game};
+
+lazy_static! {
+    static ref DAILY_HISTORY_PATH: String = format!("{}/.config/tetris_daily.txt", home_dir().unwrap().to_str().unwrap());
+}
+
+// Days since the Unix epoch, the same for every player at any moment
+pub fn utc_day() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / 86400
+}
+
+// Converts days since the Unix epoch to a (year, month, day) date in the proleptic Gregorian calendar
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
+
+pub fn record_daily(game: &Game, utc_day: u64) -> Result<()> {
+    let (year, month, day) = civil_date(utc_day);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&*DAILY_HISTORY_PATH)?;
+
+    writeln!(
+        file,
+        "{:04}-{:02}-{:02}  {}  SCORE: {}  LEVEL: {}  LINES: {}  TIME: {}{}",
+        year,
+        month,
+        day,
+        game.mode.name(),
+        game.score,
+        game.level,
+        game.lines,
+        format_duration(game.time_played()),
+        if game.complete { "  COMPLETE" } else { "" },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(10956), (1999, 12, 31));
+        assert_eq!(civil_date(11016), (2000, 2, 29));
+        assert_eq!(civil_date(11017), (2000, 3, 1));
+        assert_eq!(civil_date(20742), (2026, 10, 16));
+        assert_eq!(civil_date(47541), (2100, 3, 1));
+    }
+}
@@ -12,8 +12,8 @@ macro_rules! debug_println {
 
         let mut pipe = OpenOptions::new()
             .write(true)
-            .open(crate::debug::DEBUG_PATH)
-            .unwrap_or_else(|_| panic!("failed to open {}", crate::debug::DEBUG_PATH));
+            .open($crate::debug::DEBUG_PATH)
+            .unwrap_or_else(|_| panic!("failed to open {}", $crate::debug::DEBUG_PATH));
 
         writeln!(pipe, $($args)*).unwrap();
         pipe.flush().unwrap();
@@ -1,52 +1,298 @@
-use std::io::{Result, Stdout};
+use std::{fmt, io::{Result, Stdout}, mem::take, time::Duration};
 use::std::io::{stdout, Write};
 use crossterm::{
-    execute, QueueableCommand,
+    execute, Command, QueueableCommand,
     cursor::MoveTo,
-    style::{Color, ContentStyle, Print, PrintStyledContent, StyledContent, Stylize},
+    style::{Color, Print, PrintStyledContent, StyledContent, Stylize},
     terminal::{self, Clear, ClearType},
 };
 
-use crate::{game::Game, tetromino::{Tetromino, TetrominoVariant}};
+use strum_macros::{Display as EnumDisplay, EnumIter, EnumString};
+use rand::{thread_rng, Rng};
+use tokio::time::Instant;
+
+use crate::{
+    chaos::ChaosEvent,
+    config,
+    game::{Game, LineClear, Spin},
+    menu::{SettingItem, PAUSE_ITEMS, SETTING_ITEMS},
+    mode::Mode,
+    particle::Particle,
+    tetromino::{Tetromino, TetrominoVariant},
+};
 
 pub type Dimension = (i32, i32);
 
-pub const BOARD_DIMENSION: Dimension = (10, 20);
+// Like `EnableMouseCapture` without reporting movement, which would redraw menus on every move
+pub struct EnableMouseClicks;
+
+impl Command for EnableMouseClicks {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        // Normal tracking, with coordinates in SGR mode so they aren't limited to 223
+        f.write_str("\x1B[?1000h\x1B[?1006h")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> Result<()> {
+        crossterm::event::EnableMouseCapture.execute_winapi()
+    }
+
+    #[cfg(windows)]
+    fn is_ansi_code_supported(&self) -> bool {
+        false
+    }
+}
+
+pub const DEFAULT_BOARD_DIMENSION: Dimension = (10, 20);
 
 pub const CLEAR: &str = "        ";
 
+// Choices at the bottom of the results screen, which can also be clicked
+const RESULTS_RESTART: &str = "[R] RESTART";
+const RESULTS_QUIT: &str = "[ESC] QUIT";
+
+const NEXT_PREVIEWS: usize = 3;
+
+// Columns taken up by the panels on either side of the board
+const LEFT_PANEL_WIDTH: u16 = 12;
+const RIGHT_PANEL_WIDTH: u16 = 16;
+
+// Most extra space put between the board and the panels on large terminals
+const MAX_PANEL_GAP: u16 = 4;
+
+const PARTICLES_PER_ROW: usize = 6;
+
+// Time a callout stays up, fading from bold to dim
+const CALLOUT_DURATION: Duration = Duration::from_millis(1500);
+
+// Screen row of the top of the callout, between the hold preview and the stats sidebar
+const CALLOUT_ROW: u16 = 8;
+
+// Chains get hotter colors the longer they run
+fn chain_color(chain: i32) -> Color {
+    match chain {
+        ..=2 => Color::Yellow,
+        3..=5 => Color::DarkYellow,
+        6..=9 => Color::Red,
+        _ => Color::Magenta,
+    }
+}
+
+// Names the clear over a few lines, short enough to fit beside the board
+fn callout(clear: &LineClear) -> Vec<String> {
+    // A piece locking during another clear can complete more than four rows
+    let lines = match clear.lines {
+        1 => "SINGLE",
+        2 => "DOUBLE",
+        3 => "TRIPLE",
+        4 => "TETRIS",
+        _ => "MULTI",
+    };
+    let mut callout = if clear.perfect {
+        vec![String::from("PERFECT"), String::from("CLEAR!")]
+    } else {
+        match clear.spin {
+            Spin::Full => vec![String::from("T-SPIN"), format!("{}!", lines)],
+            Spin::Mini => vec![String::from("MINI SPIN"), format!("{}!", lines)],
+            Spin::None => vec![format!("{}!", lines)],
+        }
+    };
+    if clear.combo > 0 {
+        callout.push(format!("COMBO x{}", clear.combo));
+    }
+    if clear.back_to_back > 0 {
+        callout.push(format!("B2B x{}", clear.back_to_back));
+    }
+    callout
+}
+
+// Time the board spends at each end of a bounce while shaking
+const SHAKE_PERIOD: Duration = Duration::from_millis(50);
+
+// Time left on the garbage timer when the garbage meter turns red
+const GARBAGE_WARNING: Duration = Duration::from_secs(1);
+
+// Screen row of the top border of the hold and next boxes
+const PREVIEW_TOP: u16 = 2;
+const PARTNER_HOLD_TOP: u16 = PREVIEW_TOP + 5;
+
+// Interior width of the hold and next boxes, which fits four cells
+const PREVIEW_WIDTH: u16 = 8;
+
+#[derive(Clone, Copy, EnumDisplay, EnumIter, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum LineClearAnimation { Flash, Sweep, Dissolve, Collapse }
+
+// Fill glyphs of each piece in colorblind mode, in the order of `TetrominoVariant`
+const PATTERNS: [&str; 7] = ["=", "<", ">", "o", "/", "+", "\\"];
+
+// Foreground color of glyphs drawn in a piece's color, which high contrast mode leaves to the terminal
+fn ink(color: Color) -> Color {
+    if *config::HIGH_CONTRAST { Color::Reset } else { color }
+}
+
+// Pieces are recognized by their theme color, since that is all the stack keeps of them
+fn pattern(color: Color) -> Option<&'static str> {
+    if !config::settings().colorblind && !*config::HIGH_CONTRAST {
+        return None
+    }
+    config::theme().pieces.iter().position(|&piece| piece == color).map(|i| PATTERNS[i])
+}
+
+#[derive(Clone, Copy, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum BlockStyle { Solid, Shaded, Bracketed, Ascii }
+
+impl BlockStyle {
+    // Content of the left or right half of a block, as every block is two columns wide
+    fn block(&self, color: Color, left: bool, background: Color) -> StyledContent<&'static str> {
+        // Garbage has no pattern of its own
+        if *config::HIGH_CONTRAST {
+            return pattern(color).unwrap_or("#").stylize()
+        }
+        if let Some(pattern) = pattern(color) {
+            return match self {
+                BlockStyle::Solid => pattern.with(Color::Black).on(color),
+                _ => pattern.with(color).on(background),
+            }
+        }
+        match self {
+            BlockStyle::Solid => " ".on(color),
+            BlockStyle::Shaded => "▓".with(color).on(background),
+            BlockStyle::Bracketed => if left { "[" } else { "]" }.with(color).on(background),
+            BlockStyle::Ascii => "#".with(color).on(background),
+        }
+    }
+
+    // A locking piece is drawn differently from a placed one so the lock delay can be seen
+    fn locking(&self, color: Color, background: Color) -> StyledContent<&'static str> {
+        match self {
+            BlockStyle::Shaded => "▒".with(ink(color)).on(background),
+            BlockStyle::Ascii => "%".with(ink(color)).on(background),
+            _ => "▓".with(ink(color)).on(background),
+        }
+    }
+
+    fn ghost(&self, color: Color, left: bool, background: Color) -> StyledContent<&'static str> {
+        match self {
+            BlockStyle::Solid | BlockStyle::Shaded => "░".with(ink(color)).on(background),
+            BlockStyle::Bracketed => if left { "[" } else { "]" }.with(ink(color)).on(background).dim(),
+            BlockStyle::Ascii => "+".with(ink(color)).on(background),
+        }
+    }
+}
+
+// Whether the animation has removed a block in a row being cleared, which flashing rows never do
+fn line_cleared(progress: f32, row: usize, column: usize, width: usize) -> bool {
+    match config::settings().line_clear_animation {
+        LineClearAnimation::Flash => false,
+        LineClearAnimation::Sweep => (column as f32 + 0.5) / width as f32 <= progress,
+        LineClearAnimation::Dissolve => {
+            let noise = (row * 7 + column * 13 + row * column * 5) % width;
+            (noise as f32 + 0.5) / width as f32 <= progress
+        },
+        LineClearAnimation::Collapse => {
+            let distance = (column as f32 + 0.5 - width as f32 / 2.0).abs();
+            distance / (width as f32 / 2.0) <= progress
+        },
+    }
+}
+
+// Content of a block in a row being cleared, or None once the animation has removed it
+fn line_clear_cell(progress: f32, row: usize, column: usize, width: usize, color: Color, left: bool) -> Option<StyledContent<&'static str>> {
+    if config::settings().line_clear_animation == LineClearAnimation::Flash {
+        Some("▓".with(ink(Color::White)).on(config::theme().background))
+    } else if line_cleared(progress, row, column, width) {
+        None
+    } else {
+        Some(config::BLOCK_STYLE.block(color, left, config::theme().background))
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    format!(
+        "{:02}:{:02}.{:03}",
+        duration.as_secs() / 60,
+        duration.as_secs() % 60,
+        duration.subsec_millis(),
+    )
+}
+
 pub struct Display {
     pub stdout: Stdout,
     pub terminal_size: (u16, u16),
+    pub board_dimension: Dimension,
     pub board_x: (u16, u16),
     pub board_y: (u16, u16),
-    pub prev_next: Option<TetrominoVariant>,
+    // Left edges of the panels beside the board, which move with the terminal size
+    pub hold_x: u16,
+    pub next_x: u16,
+    // Rows the panels are moved down by, which puts them below the board on narrow terminals
+    pub panel_y: u16,
+    // Heading drawn above the board
+    pub title: String,
+    // Draws each board cell one column wide and two board rows to a terminal row
+    pub half_block: bool,
+    // Whether the last frame printed text wider than the board, which is cleaned up once it is gone
+    pub overlay_overflow: bool,
+    // Rows the board is currently moved down by a screen shake
+    pub shake_offset: u16,
+    pub particles: Vec<Particle>,
+    pub particles_updated: Instant,
+    // Start of the line clear that particles were last spawned for
+    pub particle_clear: Option<Instant>,
+    pub prev_next: Option<Vec<TetrominoVariant>>,
     pub prev_hold: Option<TetrominoVariant>,
+    // Piece held by the co-op partner, or None until its box has been drawn
+    pub prev_partner_hold: Option<Option<TetrominoVariant>>,
+    // Board cells as last written to the terminal, so only the ones that change are redrawn
+    pub prev_board: Vec<Vec<Option<StyledContent<&'static str>>>>,
+    // Left column, top row, width, and line count of the last screen drawn over the whole terminal
+    pub screen_area: (u16, u16, u16, usize),
+    // Row of the first item of the pause menu as it was last drawn
+    pub pause_items_y: u16,
 }
 
 impl Display {
-    pub fn new() -> Result<Self> {
+    pub fn new(board_dimension: Dimension) -> Result<Self> {
         let stdout = stdout();
 
         let terminal_size = terminal::size().unwrap();
 
         let board_x = (
-            terminal_size.0 / BOARD_DIMENSION.0 as u16 * 2 / 2,
-            terminal_size.0 / BOARD_DIMENSION.0 as u16 + BOARD_DIMENSION.0 as u16 * 2 + 2,
+            terminal_size.0 / board_dimension.0 as u16 * 2 / 2,
+            terminal_size.0 / board_dimension.0 as u16 + board_dimension.0 as u16 * 2 + 2,
         );
 
+        let half_block = *config::HALF_BLOCK;
+
         let board_y = (
             0,
-            BOARD_DIMENSION.1 as u16 + 2,
+            if half_block { (board_dimension.1 as u16).div_ceil(2) } else { board_dimension.1 as u16 } + 2,
         );
 
         Ok(Display {
             stdout,
             terminal_size,
+            board_dimension,
             board_x,
             board_y,
+            hold_x: 0,
+            next_x: 0,
+            panel_y: 0,
+            title: String::from("TETRIS"),
+            half_block,
+            overlay_overflow: false,
+            shake_offset: 0,
+            particles: Vec::new(),
+            particles_updated: Instant::now(),
+            particle_clear: None,
             prev_next: None,
             prev_hold: None,
+            prev_partner_hold: None,
+            prev_board: Vec::new(),
+            screen_area: (0, 0, 0, 0),
+            pause_items_y: 0,
         })
     }
 
@@ -55,20 +301,60 @@ impl Display {
 
         self.terminal_size = terminal::size().unwrap();
 
-        self.board_x = (
-            self.terminal_size.0 / 2 - BOARD_DIMENSION.0 as u16 * 2 / 2,
-            self.terminal_size.0 / 2 - BOARD_DIMENSION.0 as u16 + BOARD_DIMENSION.0 as u16 * 2 + 2,
-        );
+        let board_width = self.board_dimension.0 as u16 * if self.half_block { 1 } else { 2 } + 2;
+
+        if self.terminal_size.0 < board_width + LEFT_PANEL_WIDTH + RIGHT_PANEL_WIDTH {
+            // The panels don't fit beside the board, so they sit side by side below it, with the board
+            // centered over them
+            let width = board_width.max(LEFT_PANEL_WIDTH + RIGHT_PANEL_WIDTH);
+            let left = (self.terminal_size.0.saturating_sub(width) / 2).max(1);
+            let board_left = left + (width - board_width) / 2;
+
+            self.board_x = (board_left, board_left + board_width);
+            self.hold_x = left;
+            self.next_x = left + LEFT_PANEL_WIDTH;
+            self.panel_y = self.board_y.1 - self.shake_offset - 1;
+        } else {
+            // The board is centered when the panels fit on both sides of it, and otherwise moved left
+            // so that the wider panels on the right keep as much room as possible
+            let spare = self.terminal_size.0 - board_width - LEFT_PANEL_WIDTH - RIGHT_PANEL_WIDTH;
+            let gap = (spare / 4).min(MAX_PANEL_GAP);
+            let board_left = (self.terminal_size.0 / 2)
+                .saturating_sub(self.board_dimension.0 as u16)
+                .min(self.terminal_size.0 - board_width - RIGHT_PANEL_WIDTH - gap)
+                .max(LEFT_PANEL_WIDTH + gap);
+
+            self.board_x = (board_left, board_left + board_width);
+            self.hold_x = board_left - LEFT_PANEL_WIDTH - gap + 1;
+            self.next_x = self.board_x.1 + 1 + gap;
+            self.panel_y = 0;
+        }
 
         self.prev_hold = None;
+        self.prev_partner_hold = None;
         self.prev_next = None;
+        self.draw_board()?;
+        self.draw_preview_box(self.hold_x, PREVIEW_TOP, "HOLD", 2)?;
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        self.stdout.flush()
+    }
+
+    // Draws the border and empty cells of the board along with the title above it
+    fn draw_board(&mut self) -> Result<()> {
+        self.prev_board = vec![
+            vec![None; (self.board_x.1 - self.board_x.0 - 2) as usize];
+            (self.board_y.1 - self.board_y.0 - 2) as usize
+        ];
 
         for x in self.board_x.0..self.board_x.1 {
+            let dotted = self.dotted(x);
             for y in self.board_y.0..self.board_y.1 {
                 self.stdout
                     .queue(MoveTo(x, y))?
-                    .queue(Print(
-                        if x == self.board_x.0 && y == 0 {
+                    .queue(PrintStyledContent(
+                        if x == self.board_x.0 && y == self.board_y.0 {
                             "╔"
                         } else if x == self.board_x.0 && y == self.board_y.1 - 1 {
                             "╚"
@@ -80,34 +366,114 @@ impl Display {
                             "║"
                         } else if y == self.board_y.0 || y == self.board_y.1 - 1 {
                             "═"
-                        } else if x % 2 != self.terminal_size.0 / 2 % 2 {
+                        } else if dotted {
                             "."
                         } else {
                             " "
                         }
+                        .with(if x == self.board_x.0 || x == self.board_x.1 - 1 || y == self.board_y.0 || y == self.board_y.1 - 1 {
+                            config::theme().border
+                        } else {
+                            Color::Reset
+                        })
+                        .on(config::theme().background)
                     ))?;
             }
         }
 
+        // Titles wider than the board are cut off at its border
+        let width = (self.board_x.1 - self.board_x.0) as usize;
+        let title: String = self.title.chars().take(width).collect();
+        let title_x = self.board_x.0 + (width - title.chars().count()) as u16 / 2;
+
         self.stdout
-            .queue(MoveTo(self.board_x.0 + (self.board_x.1 - self.board_x.0) / 2 - 3, 0))?
-            .queue(PrintStyledContent("TETRIS".bold()))?
-            .queue(MoveTo(self.board_x.1 + 1, 2))?
-            .queue(Print("NEXT:"))?
-            .queue(MoveTo(self.board_x.0 - 9, 2))?
-            .queue(Print("HOLD:"))?
+            .queue(MoveTo(title_x, 0))?
+            .queue(PrintStyledContent(config::theme().text.apply(title).bold()))?
             .queue(MoveTo(0, 0))?;
 
-        Ok(self.stdout.flush()?)
+        Ok(())
+    }
+
+    // Moves the board down by `offset` rows, clearing where it was along with the garbage meter
+    fn shake(&mut self, offset: u16) -> Result<()> {
+        for y in self.board_y.0..self.board_y.1 {
+            self.stdout
+                .queue(MoveTo(self.board_x.0 - 1, y))?
+                .queue(Print(" ".repeat((self.board_x.1 - self.board_x.0 + 1) as usize)))?;
+        }
+
+        let rows = self.board_y.1 - self.board_y.0;
+        self.board_y = (offset, offset + rows);
+        self.shake_offset = offset;
+
+        self.draw_board()
+    }
+
+    // The board bounces down a row and back every SHAKE_PERIOD until the shake runs out, as long as the
+    // terminal has a spare row below it
+    fn shake_offset(&self, game: &Game) -> u16 {
+        let Some((start, duration)) = game.shake else {
+            return 0
+        };
+        let elapsed = start.elapsed();
+        if elapsed >= duration || self.board_y.1 - self.shake_offset >= self.terminal_size.1 || game.paused {
+            return 0
+        }
+        (elapsed.as_millis() / SHAKE_PERIOD.as_millis()).is_multiple_of(2) as u16
     }
 
     pub fn render(&mut self, game: &Game) -> Result<()> {
-        self.render_board(game)?
-            .render_hold(game)?
+        let overflowed = take(&mut self.overlay_overflow);
+
+        let offset = self.shake_offset(game);
+        if offset != self.shake_offset {
+            self.shake(offset)?;
+        }
+
+        self.update_particles(game);
+
+        if self.half_block {
+            self.render_board_half_block(game)?;
+        } else {
+            self.render_board(game)?;
+        }
+
+        self.render_particles()?;
+
+        self.render_hold(game)?
+            .render_partner_hold(game)?
             .render_next(game)?
-            .render_stats(game)?;
+            .render_stats(game)?
+            .render_garbage_meter(game)?
+            .render_callout(game)?
+            .render_chains(game)?
+            .render_paused(game)?
+            .render_chaos_event(game)?;
+
+        // Text that ran over the border and panels is cleared by drawing everything again
+        if overflowed && !self.overlay_overflow {
+            self.draw()?;
+        }
 
-        Ok(self.stdout.flush()?)
+        self.stdout.flush()
+    }
+
+    // Empty board cells alternate between a dot and a blank so the columns can be counted
+    fn dotted(&self, x: u16) -> bool {
+        !self.half_block && (x - self.board_x.0) % 2 != self.board_dimension.0 as u16 % 2
+    }
+
+    // Terminal rows taken up by a number of board rows
+    fn terminal_rows(&self, rows: u16) -> u16 {
+        if self.half_block { rows.div_ceil(2) } else { rows }
+    }
+
+    // Bottom of the stats, which stay below the next previews when the board is short
+    fn hud_bottom(&self) -> u16 {
+        if self.panel_y > 0 {
+            return self.panel_y + DEFAULT_BOARD_DIMENSION.1 as u16 + 2
+        }
+        (self.board_y.1 - self.shake_offset).max(DEFAULT_BOARD_DIMENSION.1 as u16 + 2)
     }
 
     fn tetromino_at_position(&self, tetromino: &Tetromino, pos: &Dimension) -> bool {
@@ -120,43 +486,77 @@ impl Display {
     }
 
     fn render_board(&mut self, game: &Game) -> Result<&mut Self> {
+        let show_ghost = config::settings().show_ghost;
+
         for x in self.board_x.0 + 1..self.board_x.1 - 1 {
             for y in self.board_y.0 + 1..self.board_y.1 - 1 {
                 let pos = &(x as i32, y as i32);
 
-                let mut content = StyledContent::new(ContentStyle::new(),
-                    if x % 2 != self.terminal_size.0 / 2 % 2 {
-                        "."
-                    } else {
-                        " "
+                let left = (x - self.board_x.0 - 1).is_multiple_of(2);
+
+                let mut content = if self.dotted(x) { "." } else { " " }.on(config::theme().background);
+
+                for player in game.players() {
+                    if let Some(ghost) = player.ghost.as_ref().filter(|_| show_ghost) {
+                        if self.tetromino_at_position(ghost, pos) {
+                            content = config::BLOCK_STYLE.ghost(player.falling.color, left, config::theme().background);
+                        }
                     }
-                );
+                }
 
-                if let Some(ghost) = &game.ghost {
-                    if self.tetromino_at_position(ghost, pos) {
-                        content = "░".with(game.falling.color);
+                // Hinted placements are outlined with brackets so they stand apart from the ghost
+                for player in game.players() {
+                    if let Some(hint) = &player.hint {
+                        if !player.spawning && hint.iter().any(|&(hint_x, hint_y)| {
+                            self.board_y.1 as i32 - hint_y - 2 == pos.1 &&
+                            (pos.0 - self.board_x.0 as i32 - 1) / 2 == hint_x
+                        }) {
+                            content = if left { "[" } else { "]" }
+                                .with(ink(player.falling.color))
+                                .on(config::theme().background);
+                        }
                     }
                 }
 
-                if self.tetromino_at_position(&game.falling, pos) {
-                    content = if game.locking {
-                        "▓".with(game.falling.color)
-                    } else {
-                         " ".on(game.falling.color)
-                    };
+                for player in game.players() {
+                    if !player.spawning && self.tetromino_at_position(&player.falling, pos) {
+                        content = if player.locking {
+                            config::BLOCK_STYLE.locking(player.falling.color, config::theme().background)
+                        } else {
+                            config::BLOCK_STYLE.block(player.falling.color, left, config::theme().background)
+                        };
+                    }
                 }
 
                 let i = (self.board_y.1 - 2 - y) as usize;
                 let j = ((x - self.board_x.0 - 1) / 2) as usize;
 
-                if let Some(color) = game.stack[i][j] {
-                    content = if game.clearing.get(&i).is_some() {
-                        "▓".with(Color::White)
+                let hidden = game.chaos_event == Some(ChaosEvent::InvisibleStack);
+
+                if let Some(color) = game.stack[i][j].filter(|_| !hidden || game.clearing.contains(&i)) {
+                    if game.clearing.contains(&i) {
+                        let progress = game.line_clear_progress();
+                        if let Some(cell) = line_clear_cell(progress, i, j, game.dimension.0 as usize, color, left) {
+                            content = cell;
+                        }
                     } else {
-                        " ".on(color)
+                        content = config::BLOCK_STYLE.block(color, left, config::theme().background);
                     }
                 }
 
+                if game.paused {
+                    content = match content.style().background_color {
+                        Some(color) if color != config::theme().background => "▒".with(color).dim(),
+                        _ => content.dim(),
+                    };
+                }
+
+                let prev = &mut self.prev_board[(y - self.board_y.0 - 1) as usize][(x - self.board_x.0 - 1) as usize];
+                if *prev == Some(content) {
+                    continue
+                }
+                *prev = Some(content);
+
                 self.stdout
                     .queue(MoveTo(x, y))?
                     .queue(PrintStyledContent(content))?;
@@ -166,69 +566,532 @@ impl Display {
         Ok(self)
     }
 
-    fn render_hold(&mut self, game: &Game) -> Result<&mut Self> {
-        if let Some(holding) = &game.holding {
-            if self.prev_hold == Some(holding.variant) {
-                return Ok(self)
+    // Each terminal row shows two board rows with a half block, its upper half in the foreground color
+    // and its lower half in the background color. Ghosts and hints are shown in grey, as there is no
+    // room for a glyph
+    fn render_board_half_block(&mut self, game: &Game) -> Result<&mut Self> {
+        let show_ghost = config::settings().show_ghost;
+        let hidden = game.chaos_event == Some(ChaosEvent::InvisibleStack);
+        let progress = game.line_clear_progress();
+        let background = config::theme().background;
+
+        let cell_color = |i: usize, j: usize| -> Option<Color> {
+            let position = (j as i32, i as i32);
+            if let Some(player) = game.players().find(|player| !player.spawning && player.falling.shape.contains(&position)) {
+                return Some(player.falling.color)
             }
-            self.prev_hold = Some(holding.variant);
+            if let Some(color) = game.stack[i][j] {
+                if game.clearing.contains(&i) {
+                    return match config::settings().line_clear_animation {
+                        LineClearAnimation::Flash => Some(Color::White),
+                        _ if line_cleared(progress, i, j, game.dimension.0 as usize) => None,
+                        _ => Some(color),
+                    }
+                }
+                if !hidden {
+                    return Some(color)
+                }
+            }
+            let ghosted = show_ghost && game.players().any(|player| {
+                player.ghost.as_ref().is_some_and(|ghost| ghost.shape.contains(&position))
+            });
+            let hinted = game.players().any(|player| {
+                !player.spawning && player.hint.as_ref().is_some_and(|hint| hint.contains(&position))
+            });
+            if ghosted || hinted { Some(Color::DarkGrey) } else { None }
+        };
+
+        for y in self.board_y.0 + 1..self.board_y.1 - 1 {
+            let lower = 2 * (self.board_y.1 - 2 - y) as usize;
+            for x in self.board_x.0 + 1..self.board_x.1 - 1 {
+                let j = (x - self.board_x.0 - 1) as usize;
+
+                let upper_color = if lower + 1 < game.dimension.1 as usize { cell_color(lower + 1, j) } else { None };
+                let mut content = match (upper_color, cell_color(lower, j)) {
+                    (None, None) => " ".on(background),
+                    (Some(upper), None) => "▀".with(upper).on(background),
+                    (None, Some(lower)) => "▄".with(lower).on(background),
+                    (Some(upper), Some(lower)) => "▀".with(upper).on(lower),
+                };
+
+                if game.paused {
+                    content = content.dim();
+                }
+
+                let prev = &mut self.prev_board[(y - self.board_y.0 - 1) as usize][j];
+                if *prev == Some(content) {
+                    continue
+                }
+                *prev = Some(content);
 
-            self.stdout
-                .queue(MoveTo(self.board_x.0 - 10, 4))?
-                .queue(Print(CLEAR))?
-                .queue(MoveTo(self.board_x.0 - 10, 5))?
-                .queue(Print(CLEAR))?;
-            for position in holding.shape.iter().map(|&(x, y)| (x as u16, y as u16)) {
                 self.stdout
-                    .queue(MoveTo((position.0 - 3) * 2 + self.board_x.0 - 10, self.board_y.1 - position.1 + 1))?
-                    .queue(PrintStyledContent(" ".on(holding.color)))?
-                    .queue(MoveTo((position.0 - 3) * 2 + self.board_x.0 - 9, self.board_y.1 - position.1 + 1))?
-                    .queue(PrintStyledContent(" ".on(holding.color)))?;
+                    .queue(MoveTo(x, y))?
+                    .queue(PrintStyledContent(content))?;
             }
         }
 
+        self.stdout.queue(MoveTo(0, 0))?;
+
         Ok(self)
     }
 
-    fn render_next(&mut self, game: &Game) -> Result<&mut Self> {
-        if let Some(next) = &game.next.get(0) {
-            if self.prev_next == Some(next.variant) {
-                return Ok(self)
+    // Moves the particles out on the board, throwing new ones out of rows that just started clearing.
+    // The cells they covered are marked to be redrawn under them
+    fn update_particles(&mut self, game: &Game) {
+        // The board may have moved since they were drawn, so they can end up outside of it
+        for particle in self.particles.iter() {
+            let row = (particle.y as u16).checked_sub(self.board_y.0 + 1);
+            let column = (particle.x as u16).checked_sub(self.board_x.0 + 1);
+            if let Some(cell) = row
+                .zip(column)
+                .and_then(|(row, column)| self.prev_board.get_mut(row as usize)?.get_mut(column as usize))
+            {
+                *cell = None;
+            }
+        }
+
+        let elapsed = self.particles_updated.elapsed();
+        self.particles_updated = Instant::now();
+
+        if game.paused {
+            self.particles.clear();
+            return
+        }
+
+        let (left, right) = (self.board_x.0 as f32 + 1.0, self.board_x.1 as f32 - 1.0);
+        let (top, bottom) = (self.board_y.0 as f32 + 1.0, self.board_y.1 as f32 - 1.0);
+        for particle in self.particles.iter_mut() {
+            particle.update(elapsed);
+        }
+        self.particles.retain(|particle| {
+            !particle.expired() &&
+            (left..right).contains(&particle.x) &&
+            (top..bottom).contains(&particle.y)
+        });
+
+        if !*config::PARTICLES || game.clearing.is_empty() || self.particle_clear == Some(game.clear_start) {
+            return
+        }
+        self.particle_clear = Some(game.clear_start);
+
+        let mut rng = thread_rng();
+        let cell_width = if self.half_block { 1 } else { 2 };
+        for &row in game.clearing.iter() {
+            let y = self.board_y.1 - 1 - self.terminal_rows(row as u16 + 1);
+            for _ in 0..PARTICLES_PER_ROW {
+                let column = rng.gen_range(0..game.dimension.0 as usize);
+                let x = self.board_x.0 + 1 + column as u16 * cell_width;
+                let color = game.stack[row][column].unwrap_or(Color::White);
+                self.particles.push(Particle::new(x as f32 + 0.5, y as f32 + 0.5, color, &mut rng));
             }
-            self.prev_next = Some(next.variant);
         }
+    }
+
+    fn render_particles(&mut self) -> Result<&mut Self> {
+        for particle in self.particles.iter() {
+            self.stdout
+                .queue(MoveTo(particle.x as u16, particle.y as u16))?
+                .queue(PrintStyledContent(particle.glyph.with(ink(particle.color)).on(config::theme().background)))?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    // Draws an empty box with its title set into the top border, clearing anything previously inside it
+    fn draw_preview_box(&mut self, x: u16, top: u16, title: &str, rows: u16) -> Result<()> {
+        let border = config::theme().border;
+        let fill = "═".repeat((PREVIEW_WIDTH as usize).saturating_sub(title.len() + 1));
+        let top = self.panel_y + top;
 
-        for (i, tetromino) in game.next.iter().enumerate() {
+        self.stdout
+            .queue(MoveTo(x, top))?
+            .queue(PrintStyledContent("╔═".with(border)))?
+            .queue(PrintStyledContent(config::theme().text.apply(title)))?
+            .queue(PrintStyledContent(format!("{}╗", fill).with(border)))?;
+
+        for y in top + 1..=top + rows {
             self.stdout
-                .queue(MoveTo(self.board_x.1 + 1, 4 + (i as u16 * 3)))?
+                .queue(MoveTo(x, y))?
+                .queue(PrintStyledContent("║".with(border)))?
                 .queue(Print(CLEAR))?
-                .queue(MoveTo(self.board_x.1 + 1, 5 + (i as u16 * 3)))?
-                .queue(Print(CLEAR))?;
-            for position in tetromino.shape.iter().map(|&(x, y)| (x as u16, y as u16)) {
+                .queue(PrintStyledContent("║".with(border)))?;
+        }
+
+        self.stdout
+            .queue(MoveTo(x, top + rows + 1))?
+            .queue(PrintStyledContent(format!("╚{}╝", "═".repeat(PREVIEW_WIDTH as usize)).with(border)))?;
+
+        Ok(())
+    }
+
+    // Draws a piece in its spawn orientation centered within the four cells starting at (x, y), with
+    // pieces that only take up one row sitting on the lower of the two
+    fn draw_preview(&mut self, tetromino: &Tetromino, x: u16, y: u16) -> Result<()> {
+        let min_x = tetromino.shape.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let max_x = tetromino.shape.iter().map(|&(x, _)| x).max().unwrap_or(0);
+        let min_y = tetromino.shape.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let pad = PREVIEW_WIDTH / 2 - (max_x - min_x + 1) as u16;
+
+        for &(cell_x, cell_y) in tetromino.shape.iter() {
+            let column = x + pad + (cell_x - min_x) as u16 * 2;
+            let row = y + 1 - (cell_y - min_y) as u16;
+            self.stdout
+                .queue(MoveTo(column, row))?
+                .queue(PrintStyledContent(config::BLOCK_STYLE.block(tetromino.color, true, Color::Reset)))?
+                .queue(MoveTo(column + 1, row))?
+                .queue(PrintStyledContent(config::BLOCK_STYLE.block(tetromino.color, false, Color::Reset)))?;
+        }
+
+        Ok(())
+    }
+
+    fn render_hold(&mut self, game: &Game) -> Result<&mut Self> {
+        let hold = game.player.holding.as_ref().map(|holding| holding.variant);
+        if self.prev_hold == hold {
+            return Ok(self)
+        }
+        self.prev_hold = hold;
+
+        self.draw_preview_box(self.hold_x, PREVIEW_TOP, "HOLD", 2)?;
+
+        if let Some(holding) = &game.player.holding {
+            self.draw_preview(holding, self.hold_x + 1, self.panel_y + PREVIEW_TOP + 1)?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    // The co-op partner's hold sits just below the first one
+    fn render_partner_hold(&mut self, game: &Game) -> Result<&mut Self> {
+        let Some(partner) = &game.partner else {
+            return Ok(self)
+        };
+        let hold = partner.holding.as_ref().map(|holding| holding.variant);
+        if self.prev_partner_hold == Some(hold) {
+            return Ok(self)
+        }
+        self.prev_partner_hold = Some(hold);
+
+        self.draw_preview_box(self.hold_x, PARTNER_HOLD_TOP, "HOLD 2", 2)?;
+
+        if let Some(holding) = &partner.holding {
+            self.draw_preview(holding, self.hold_x + 1, self.panel_y + PARTNER_HOLD_TOP + 1)?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    fn render_next(&mut self, game: &Game) -> Result<&mut Self> {
+        let previews = game.ruleset.previews.min(NEXT_PREVIEWS);
+        let next: Vec<TetrominoVariant> = game.next.iter().take(previews).map(|tetromino| tetromino.variant).collect();
+        if self.prev_next.as_ref() == Some(&next) {
+            return Ok(self)
+        }
+        self.prev_next = Some(next);
+
+        // Previews are stacked three rows apart, leaving a blank row between each piece
+        self.draw_preview_box(self.next_x, PREVIEW_TOP, "NEXT", (previews.max(1) as u16 * 3).saturating_sub(1))?;
+
+        for (i, tetromino) in game.next.iter().take(previews).enumerate() {
+            self.draw_preview(tetromino, self.next_x + 1, self.panel_y + PREVIEW_TOP + 1 + i as u16 * 3)?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    fn render_stats(&mut self, game: &Game) -> Result<&mut Self> {
+        let bottom = self.hud_bottom();
+
+        self.stdout
+            .queue(MoveTo(self.next_x, bottom - 5))?
+            .queue(PrintStyledContent(config::theme().text.apply(format!("SCORE: {}", game.score))))?
+            .queue(MoveTo(self.next_x, bottom - 4))?
+            .queue(PrintStyledContent(config::theme().text.apply(match game.mode.target_level() {
+                Some(target_level) => format!("LEVEL: {}/{}", game.level, target_level),
+                None => format!("LEVEL: {}", game.level),
+            })))?
+            .queue(MoveTo(self.next_x, bottom - 3))?
+            .queue(PrintStyledContent(config::theme().text.apply(match game.mode.line_goal() {
+                Some(line_goal) => format!("LINES: {}/{}", game.lines, line_goal),
+                None => format!("LINES: {}", game.lines),
+            })))?;
+
+        if matches!(game.mode, Mode::Cheese | Mode::Garbage | Mode::Survival) {
+            self.stdout
+                .queue(MoveTo(self.next_x, bottom - 7))?
+                .queue(PrintStyledContent(config::theme().text.apply(format!("GARBAGE: {}   ", game.garbage_rows))))?;
+        }
+
+        if game.mode == Mode::Combo {
+            self.stdout
+                .queue(MoveTo(self.next_x, bottom - 7))?
+                .queue(PrintStyledContent(config::theme().text.apply(format!("COMBO: {}   ", game.combo.max(0))).bold()))?;
+        }
+
+        if game.mode == Mode::Puzzle {
+            if let Some(puzzle) = game.puzzles.get(game.puzzle_index) {
                 self.stdout
-                    .queue(MoveTo((position.0 - 3) * 2 + self.board_x.1 + 2, self.board_y.1 - position.1 + 1 + (i as u16 * 3)))?
-                    .queue(PrintStyledContent(" ".on(tetromino.color)))?
-                    .queue(MoveTo((position.0 - 3) * 2 + self.board_x.1 + 1, self.board_y.1 - position.1 + 1 + (i as u16 * 3)))?
-                    .queue(PrintStyledContent(" ".on(tetromino.color)))?;
+                    .queue(MoveTo(self.next_x, bottom - 8))?
+                    .queue(PrintStyledContent(config::theme().text.apply(format!("PUZZLE {}/{}: {:<20}", game.puzzle_index + 1, game.puzzles.len(), puzzle.name))))?
+                    .queue(MoveTo(self.next_x, bottom - 7))?
+                    .queue(PrintStyledContent(config::theme().text.apply(format!("GOAL: {:<20}", puzzle.objective.name()))))?;
             }
         }
 
+        // The sidebar sits below the hold preview, where each line fits in its width
+        if config::settings().show_stats {
+            let lines = [
+                String::from("TIME:"),
+                format_duration(game.time_played()),
+                format!("PCS: {}", game.pieces),
+                format!("PPS: {:.2}", game.pieces_per_second()),
+                format!("APM: {:.1}", game.attacks_per_minute()),
+                format!("KPP: {:.2}", game.keys_per_piece()),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                self.stdout
+                    .queue(MoveTo(self.hold_x, bottom - 7 + i as u16))?
+                    .queue(PrintStyledContent(config::theme().text.apply(format!("{:<9}", line))))?;
+            }
+        }
+
+        // Ultra counts down its time limit, every other mode counts up from the start
+        self.stdout
+            .queue(MoveTo(self.next_x, bottom - 6))?
+            .queue(PrintStyledContent(config::theme().text.apply(format!("TIME: {}", format_duration(game.time_remaining().unwrap_or(game.time_played()))))))?;
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
         Ok(self)
     }
 
-    fn render_stats(&mut self, game: &Game) -> Result<&mut Self> {
+    fn render_callout(&mut self, game: &Game) -> Result<&mut Self> {
+        let clear = game.last_clear.filter(|clear| clear.time.elapsed() < CALLOUT_DURATION);
+        let elapsed = clear.map_or(Duration::ZERO, |clear| clear.time.elapsed());
+        let lines = clear.as_ref().map(callout).unwrap_or_default();
+        let row = if game.partner.is_some() { PARTNER_HOLD_TOP + 5 } else { CALLOUT_ROW };
+
+        for i in 0..4 {
+            let line = format!("{:<10}", lines.get(i).map_or("", String::as_str));
+            let content = config::theme().text.apply(line);
+            self.stdout
+                .queue(MoveTo(self.hold_x, self.panel_y + row + i as u16))?
+                .queue(PrintStyledContent(if elapsed < CALLOUT_DURATION / 3 {
+                    content.bold()
+                } else if elapsed > CALLOUT_DURATION * 2 / 3 {
+                    content.dim()
+                } else {
+                    content
+                }))?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    // The combo and back-to-back streaks sit just below the next previews while they last
+    fn render_chains(&mut self, game: &Game) -> Result<&mut Self> {
+        let bottom = self.hud_bottom();
+
+        for (i, (name, chain)) in [("COMBO", game.combo), ("B2B", game.back_to_back)].into_iter().enumerate() {
+            let text = if chain > 0 { format!("{} x{}", name, chain) } else { String::new() };
+            self.stdout
+                .queue(MoveTo(self.next_x, bottom - 10 + i as u16))?
+                .queue(PrintStyledContent(format!("{:<11}", text).with(ink(chain_color(chain))).bold()))?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    // A bar beside the left edge of the board as tall as the garbage about to rise, which turns red
+    // shortly before it does
+    fn render_garbage_meter(&mut self, game: &Game) -> Result<&mut Self> {
+        let Some(pending) = game.pending_garbage() else {
+            return Ok(self)
+        };
+
+        let remaining = game.garbage_due.saturating_duration_since(Instant::now());
+        let warning = remaining <= GARBAGE_WARNING && !game.paused;
+        let color = if warning { Color::Red } else { Color::Yellow };
+        // Without colors the warning is shown by a lighter bar
+        let glyph = if warning && *config::HIGH_CONTRAST { "▒" } else { "█" };
+
+        for y in self.board_y.0 + 1..self.board_y.1 - 1 {
+            let content = if self.board_y.1 - 1 - y <= self.terminal_rows(pending as u16) { glyph.with(ink(color)) } else { " ".stylize() };
+            self.stdout
+                .queue(MoveTo(self.board_x.0 - 1, y))?
+                .queue(PrintStyledContent(content))?;
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        Ok(self)
+    }
+
+    // Prints text centered over a row of the board, marking the cells it covers to be redrawn once it is gone
+    fn render_board_text(&mut self, content: StyledContent<&str>, y: u16) -> Result<()> {
+        let width = content.content().chars().count() as u16;
+        let x = (self.board_x.0 + (self.board_x.1 - self.board_x.0) / 2).saturating_sub(width / 2);
+
+        if x <= self.board_x.0 || x + width >= self.board_x.1 {
+            self.overlay_overflow = true;
+        }
+
+        if let Some(row) = self.prev_board.get_mut((y - self.board_y.0 - 1) as usize) {
+            for cell in row.iter_mut().skip(x.saturating_sub(self.board_x.0 + 1) as usize).take(width as usize) {
+                *cell = None;
+            }
+        }
+
         self.stdout
-            .queue(MoveTo(self.board_x.1 + 1, 17))?
-            .queue(Print(format!("SCORE: {}", game.score)))?
-            .queue(MoveTo(self.board_x.1 + 1, 18))?
-            .queue(Print(format!("LEVEL: {}", game.level)))?
-            .queue(MoveTo(self.board_x.1 + 1, 19))?
-            .queue(Print(format!("LINES: {}", game.lines)))?
+            .queue(MoveTo(x, y))?
+            .queue(PrintStyledContent(content))?
             .queue(MoveTo(0, 0))?;
 
+        Ok(())
+    }
+
+    fn render_paused(&mut self, game: &Game) -> Result<&mut Self> {
+        if game.paused {
+            let (title, items): (&str, Vec<String>) = if game.settings_open {
+                (game.settings_notice.as_deref().unwrap_or("SETTINGS"), SETTING_ITEMS.iter().map(SettingItem::label).collect())
+            } else {
+                ("PAUSED", PAUSE_ITEMS.iter().map(|item| String::from(item.name())).collect())
+            };
+            // Moved up on short boards so the last item stays inside the board
+            let y = (self.board_y.1 / 2 - 2)
+                .min(self.board_y.1.saturating_sub(items.len() as u16 + 3))
+                .max(self.board_y.0 + 1);
+            self.render_board_text(title.bold(), y)?;
+            self.pause_items_y = y + 2;
+            for (i, item) in items.iter().enumerate() {
+                let content = if i == game.pause_selected { item.as_str().reverse() } else { item.as_str().stylize() };
+                self.render_board_text(content, y + 2 + i as u16)?;
+            }
+        }
+
         Ok(self)
     }
 
+    fn render_chaos_event(&mut self, game: &Game) -> Result<&mut Self> {
+        if let Some(event) = game.chaos_event {
+            self.render_board_text(event.name().bold().reverse(), self.board_y.0 + 2)?;
+        }
+
+        Ok(self)
+    }
+
+    // Clears the screen and prints lines centered on it, with the first line as a bold title
+    fn render_screen(&mut self, lines: &[String], selected: Option<usize>) -> Result<()> {
+        execute!(self.stdout, Clear(ClearType::All))?;
+
+        self.terminal_size = terminal::size().unwrap();
+
+        let width = lines.iter().map(|line| line.len()).max().unwrap() as u16;
+        let x = (self.terminal_size.0 / 2).saturating_sub(width / 2);
+        let y = (self.terminal_size.1 / 2).saturating_sub(lines.len() as u16 / 2);
+        self.screen_area = (x, y, width, lines.len());
+
+        for (i, line) in lines.iter().enumerate() {
+            self.stdout.queue(MoveTo(x, y + i as u16))?;
+            if i == 0 {
+                self.stdout.queue(PrintStyledContent(config::theme().text.apply(line).bold()))?;
+            } else if selected == Some(i) {
+                self.stdout.queue(PrintStyledContent(config::theme().text.apply(line).reverse()))?;
+            } else {
+                self.stdout.queue(PrintStyledContent(config::theme().text.apply(line)))?;
+            }
+        }
+
+        self.stdout.queue(MoveTo(0, 0))?;
+
+        self.stdout.flush()
+    }
+
+    pub fn render_menu(&mut self, title: &str, items: &[String], selected: usize) -> Result<()> {
+        let mut lines = vec![String::from(title), String::new()];
+        lines.extend(items.iter().cloned());
+        self.render_screen(&lines, Some(selected + 2))
+    }
+
+    pub fn render_results(&mut self, game: &Game) -> Result<()> {
+        let mut lines = vec![
+            if game.complete {
+                game.mode.complete_title()
+            } else {
+                String::from("GAME OVER")
+            },
+            String::new(),
+            // Survival is scored on how long the player lasted
+            match game.mode {
+                Mode::Survival => format!("SCORE:  {}", format_duration(game.time_played())),
+                _ => format!("SCORE:  {}", game.score),
+            },
+            format!("LEVEL:  {}", game.level),
+            format!("LINES:  {}", game.lines),
+            format!("TIME:   {}", format_duration(game.time_played())),
+            format!("PIECES: {}", game.pieces),
+            format!("PPS:    {:.2}", game.pieces_per_second()),
+        ];
+
+        // Time trials list how long each level took
+        if game.mode.target_level().is_some() {
+            lines.push(String::new());
+            let mut prev_time = Duration::ZERO;
+            for (i, &time) in game.level_times.iter().enumerate() {
+                lines.push(format!("LEVEL {:<3} {}", game.start_level + i as u32, format_duration(time - prev_time)));
+                prev_time = time;
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{}  {}", RESULTS_RESTART, RESULTS_QUIT));
+
+        self.render_screen(&lines, None)
+    }
+
+    // Line of the last screen drawn at a terminal position, along with the column within the line
+    fn screen_line_at(&self, column: u16, row: u16) -> Option<(usize, u16)> {
+        let (x, y, width, lines) = self.screen_area;
+        let line = row.checked_sub(y)? as usize;
+        let offset = column.checked_sub(x)?;
+        (line < lines && offset < width).then_some((line, offset))
+    }
+
+    pub fn menu_item_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.screen_line_at(column, row)?.0.checked_sub(2)
+    }
+
+    // Counts the whole width of the board
+    pub fn pause_item_at(&self, column: u16, row: u16, items: usize) -> Option<usize> {
+        let item = row.checked_sub(self.pause_items_y)? as usize;
+        ((self.board_x.0..self.board_x.1).contains(&column) && item < items).then_some(item)
+    }
+
+    // Restart is true and quit is false
+    pub fn results_choice_at(&self, column: u16, row: u16) -> Option<bool> {
+        let (line, offset) = self.screen_line_at(column, row)?;
+        if line + 1 != self.screen_area.3 {
+            return None
+        }
+        match offset as usize {
+            offset if offset < RESULTS_RESTART.len() => Some(true),
+            offset if offset >= RESULTS_RESTART.len() + 2 => Some(false),
+            _ => None,
+        }
+    }
+
     pub fn render_debug_info(&mut self, debug_frame: u64) -> Result<&mut Self> {
         self.stdout
             .queue(MoveTo(0, 0))?
@@ -1,62 +1,285 @@
 use std::{io::Result, pin::Pin};
-use crossterm::event::{Event, KeyEvent, KeyEventKind};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use strum_macros::EnumIter;
 use tokio::time::Sleep;
 
-use crate::{config, display::Display, game::{Game, RotationDirection, ShiftDirection}};
+use crate::{
+    chaos::ChaosEvent,
+    config::{self, controls::Key},
+    display::Display,
+    game::{Game, RotationDirection, ShiftDirection},
+    menu::{PauseItem, SettingItem, PAUSE_ITEMS, SETTING_ITEMS},
+};
 
-#[derive(Clone)]
+#[derive(Clone, EnumIter, PartialEq)]
 pub enum Action {
     MoveRight,
     MoveLeft,
     RotateRight,
     RotateLeft,
+    Rotate180,
     SoftDrop,
+    SonicDrop,
     HardDrop,
     Hold,
+    Hint,
+    Pause,
     Quit,
 }
 
+impl Action {
+    pub fn name(&self) -> &str {
+        match self {
+            Action::MoveRight => "MOVE RIGHT",
+            Action::MoveLeft => "MOVE LEFT",
+            Action::RotateRight => "ROTATE RIGHT",
+            Action::RotateLeft => "ROTATE LEFT",
+            Action::Rotate180 => "ROTATE 180",
+            Action::SoftDrop => "SOFT DROP",
+            Action::SonicDrop => "SONIC DROP",
+            Action::HardDrop => "HARD DROP",
+            Action::Hold => "HOLD",
+            Action::Hint => "HINT",
+            Action::Pause => "PAUSE",
+            Action::Quit => "QUIT",
+        }
+    }
+
+    fn mirrored(&self) -> Action {
+        match self {
+            Action::MoveRight => Action::MoveLeft,
+            Action::MoveLeft => Action::MoveRight,
+            Action::RotateRight => Action::RotateLeft,
+            Action::RotateLeft => Action::RotateRight,
+            action => action.clone(),
+        }
+    }
+
+    // Keys that control the falling piece, which count towards keys per piece
+    fn moves_piece(&self) -> bool {
+        !matches!(self, Action::Hint | Action::Pause | Action::Quit)
+    }
+}
+
+// Moving left and right changes the selected setting
+fn handle_settings_menu(game: &mut Game, code: KeyCode, action: Option<Action>, display: &mut Display) -> Result<()> {
+    let item = SETTING_ITEMS[game.pause_selected];
+    game.settings_notice = None;
+    let forward = match (code, action) {
+        (KeyCode::Up, _) | (_, Some(Action::RotateRight)) => {
+            game.pause_selected = (game.pause_selected + SETTING_ITEMS.len() - 1) % SETTING_ITEMS.len();
+            return Ok(())
+        },
+        (KeyCode::Down, _) | (_, Some(Action::SoftDrop)) => {
+            game.pause_selected = (game.pause_selected + 1) % SETTING_ITEMS.len();
+            return Ok(())
+        },
+        (KeyCode::Enter, _) | (_, Some(Action::HardDrop)) if item == SettingItem::Back => {
+            game.settings_open = false;
+            game.pause_selected = PAUSE_ITEMS.iter().position(|&item| item == PauseItem::Settings).unwrap();
+            return Ok(())
+        },
+        (KeyCode::Left, _) | (_, Some(Action::MoveLeft)) => false,
+        (KeyCode::Right | KeyCode::Enter, _) | (_, Some(Action::MoveRight | Action::HardDrop)) => true,
+        _ => return Ok(()),
+    };
+    let theme = config::theme().clone();
+    game.settings_notice = item.change(forward).err().map(|_| String::from("SAVE FAILED"));
+    game.recolor(&theme);
+    // Redrawn from scratch so that nothing is left behind by a setting that was turned off
+    game.update_ghost();
+    display.draw()
+}
+
+// Rotate right and soft drop move the selection, hard drop picks an item
+fn handle_pause_menu(game: &mut Game, code: KeyCode, action: Option<Action>, display: &mut Display) -> Result<()> {
+    if game.settings_open {
+        return handle_settings_menu(game, code, action, display)
+    }
+    match (code, action) {
+        (KeyCode::Up, _) | (_, Some(Action::RotateRight)) => {
+            game.pause_selected = (game.pause_selected + PAUSE_ITEMS.len() - 1) % PAUSE_ITEMS.len();
+        },
+        (KeyCode::Down, _) | (_, Some(Action::SoftDrop)) => {
+            game.pause_selected = (game.pause_selected + 1) % PAUSE_ITEMS.len();
+        },
+        (KeyCode::Enter, _) | (_, Some(Action::HardDrop)) => match PAUSE_ITEMS[game.pause_selected] {
+            PauseItem::Resume => game.toggle_pause(),
+            PauseItem::Restart => {
+                game.restart = true;
+                game.quit = true;
+                game.end = true;
+            },
+            PauseItem::Settings => {
+                game.settings_open = true;
+                game.pause_selected = 0;
+            },
+            PauseItem::Quit => {
+                game.quit = true;
+                game.end = true;
+            },
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+// Acts on the piece of `game.player` for a key bound to one of the actions that control it
+fn handle_piece_action(
+    game: &mut Game,
+    key: Key,
+    action: Action,
+    lock_delay: &mut Pin<&mut Sleep>,
+    line_clear_delay: &mut Pin<&mut Sleep>
+) {
+    if game.key_releases {
+        match action {
+            Action::MoveRight => game.hold_shift(key.code, ShiftDirection::Right),
+            Action::MoveLeft => game.hold_shift(key.code, ShiftDirection::Left),
+            Action::RotateRight => game.player.held_rotation = Some((key.code, RotationDirection::Clockwise)),
+            Action::RotateLeft => game.player.held_rotation = Some((key.code, RotationDirection::CounterClockwise)),
+            Action::Rotate180 => game.player.held_rotation = Some((key.code, RotationDirection::Half)),
+            Action::Hold => game.player.held_hold = Some(key.code),
+            _ => (),
+        }
+    }
+    match action {
+        Action::RotateRight if game.player.spawning => {
+            game.player.buffered_rotation = Some(RotationDirection::Clockwise);
+        },
+        Action::RotateLeft if game.player.spawning => {
+            game.player.buffered_rotation = Some(RotationDirection::CounterClockwise);
+        },
+        Action::Rotate180 if game.player.spawning => {
+            game.player.buffered_rotation = Some(RotationDirection::Half);
+        },
+        Action::Hold if game.player.spawning => {
+            game.player.buffered_hold = true;
+        },
+        _ if game.player.spawning => (),
+        Action::MoveRight => {
+            game.shift(ShiftDirection::Right, lock_delay, line_clear_delay);
+        },
+        Action::MoveLeft => {
+            game.shift(ShiftDirection::Left, lock_delay, line_clear_delay);
+        },
+        Action::RotateRight => {
+            game.rotate(RotationDirection::Clockwise, lock_delay);
+        },
+        Action::RotateLeft => {
+            game.rotate(RotationDirection::CounterClockwise, lock_delay);
+        },
+        Action::Rotate180 => {
+            game.rotate(RotationDirection::Half, lock_delay);
+        },
+        Action::SoftDrop => {
+            game.soft_drop();
+        },
+        Action::SonicDrop => {
+            game.sonic_drop(lock_delay);
+        },
+        Action::HardDrop => {
+            game.hard_drop(line_clear_delay);
+        },
+        Action::Hold => {
+            game.hold();
+        },
+        Action::Hint | Action::Pause | Action::Quit => (),
+    }
+}
+
+// In co-op the keys of the second player are looked up first
+fn partner_action(game: &Game, key: Key) -> Option<Action> {
+    game.partner.as_ref().and_then(|_| config::controls::coop_action(key))
+}
+
 pub fn handle_event(
     game: &mut Game,
     event: Event,
     display: &mut Display,
     lock_delay: &mut Pin<&mut Sleep>,
+    partner_lock_delay: &mut Pin<&mut Sleep>,
     line_clear_delay: &mut Pin<&mut Sleep>
 ) -> Result<()> {
-    Ok(match event {
-        Event::Key(KeyEvent { kind, code, .. }) => {
-            if kind == KeyEventKind::Press {
-                match config::controls::ACTION_MAP.get(&code) {
-                    Some(Action::MoveRight) => {
-                        game.shift(ShiftDirection::Right, lock_delay, line_clear_delay);
-                    },
-                    Some(Action::MoveLeft) => {
-                        game.shift(ShiftDirection::Left, lock_delay, line_clear_delay);
-                    },
-                    Some(Action::RotateRight) => {
-                        game.rotate(RotationDirection::Clockwise, lock_delay);
-                    },
-                    Some(Action::RotateLeft) => {
-                        game.rotate(RotationDirection::CounterClockwise, lock_delay);
-                    },
-                    Some(Action::SoftDrop) => {
-                        game.soft_drop(lock_delay, line_clear_delay);
-                    },
-                    Some(Action::HardDrop) => {
-                        game.hard_drop(line_clear_delay);
-                    },
-                    Some(Action::Hold) => {
-                        game.hold();
-                    },
-                    Some(Action::Quit) => {
-                        game.end = true;
-                    },
-                    None => (),
-                }
+    match event {
+        Event::Key(key @ KeyEvent { kind: KeyEventKind::Press, code, .. }) => {
+            let key = Key::from(&key);
+            let partner_action = partner_action(game, key);
+            let action = partner_action.clone().or_else(|| config::controls::action(key)).map(|action| {
+                if game.chaos_event == Some(ChaosEvent::MirroredControls) { action.mirrored() } else { action }
+            });
+            if !game.paused && action.as_ref().is_some_and(Action::moves_piece) {
+                game.keys += 1;
+            }
+            match action {
+                Some(Action::Pause) => {
+                    game.toggle_pause();
+                },
+                Some(Action::Quit) => {
+                    game.quit = true;
+                    game.end = true;
+                },
+                _ if game.paused => handle_pause_menu(game, code, action, display)?,
+                Some(Action::Hint) => {
+                    game.toggle_hint();
+                },
+                Some(action) if partner_action.is_some() => {
+                    game.with_partner(|game| handle_piece_action(game, key, action, partner_lock_delay, line_clear_delay));
+                },
+                Some(action) => handle_piece_action(game, key, action, lock_delay, line_clear_delay),
+                None => (),
             }
         },
+        Event::Key(key @ KeyEvent { kind: KeyEventKind::Release, .. }) => {
+            let key = Key::from(&key);
+            match partner_action(game, key) {
+                Some(action) => {
+                    game.with_partner(|game| game.release_key(key, Some(action)));
+                },
+                None => game.release_key(key, config::controls::action(key)),
+            }
+        },
+        // The mouse only works in the pause menu, where the wheel moves the selection and clicking an
+        // item picks it
+        Event::Mouse(MouseEvent { kind, column, row, .. }) if game.paused => {
+            let items = if game.settings_open { SETTING_ITEMS.len() } else { PAUSE_ITEMS.len() };
+            let code = match kind {
+                MouseEventKind::ScrollUp => KeyCode::Up,
+                MouseEventKind::ScrollDown => KeyCode::Down,
+                MouseEventKind::Down(MouseButton::Left) => match display.pause_item_at(column, row, items) {
+                    Some(item) => {
+                        game.pause_selected = item;
+                        KeyCode::Enter
+                    },
+                    None => return Ok(()),
+                },
+                _ => return Ok(()),
+            };
+            handle_pause_menu(game, code, None, display)?;
+        },
         Event::Resize(_, _) => display.draw()?,
         _ => (),
-    })
+    }
+    Ok(())
 }
 
+// Returns whether to restart once a key is chosen on the results screen
+pub fn handle_results_event(game: &Game, event: Event, display: &mut Display) -> Result<Option<bool>> {
+    match event {
+        Event::Key(key @ KeyEvent { kind: KeyEventKind::Press, code, .. }) => {
+            match code {
+                KeyCode::Char('r') | KeyCode::Char('R') | KeyCode::Enter => return Ok(Some(true)),
+                KeyCode::Esc => return Ok(Some(false)),
+                _ => if let Some(Action::Quit) = config::controls::action(Key::from(&key)) {
+                    return Ok(Some(false))
+                },
+            }
+        },
+        Event::Mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column, row, .. }) => {
+            return Ok(display.results_choice_at(column, row))
+        },
+        Event::Resize(_, _) => display.render_results(game)?,
+        _ => (),
+    }
+    Ok(None)
+}
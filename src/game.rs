@@ -1,110 +1,643 @@
-use std::{collections::HashSet, mem::replace, pin::Pin};
+use std::{collections::HashSet, fs::read_to_string, mem::take, ops::Range, pin::Pin};
 use core::time::Duration;
-use crossterm::style::Color;
+use crossterm::{event::KeyCode, style::Color};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{rngs::StdRng, seq::{IteratorRandom, SliceRandom}, Rng, SeedableRng};
 use strum::IntoEnumIterator;
-use tokio::time::{sleep, Sleep};
+use strum_macros::EnumString;
+use tokio::time::{sleep, Instant, Sleep};
 
-use crate::{display::BOARD_DIMENSION, tetromino::*};
+use crate::{chaos::{self, ChaosEvent}, config::{self, controls::Key}, display::{Dimension, DEFAULT_BOARD_DIMENSION}, event::Action, gravity::SpeedCurve, hint::best_placement, mode::Mode, puzzle::Puzzle, rotation::KickTable, ruleset::{Randomizer, Ruleset}, tetromino::*, theme::Theme};
 
 const LOCK_RESET_LIMIT: u8 = 15;
-const LOCK_DURATION: Duration = Duration::from_millis(500);
+const BUFFER_HEIGHT: i32 = 20;
 const LINE_CLEAR_DURATION: Duration = Duration::from_millis(125);
+const SOFT_DROP_HOLD_DURATION: Duration = Duration::from_millis(100);
 
-static JLSTZ_OFFSETS: [[(i32, i32); 5]; 4] = [
-    [( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)], // North
-    [( 0,  0), ( 1,  0), ( 1, -1), ( 0,  2), ( 1,  2)], // East
-    [( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)], // South
-    [( 0,  0), (-1,  0), (-1, -1), ( 0,  2), (-1,  2)], // West
-];
-
-static I_OFFSETS: [[(i32, i32); 5]; 4] = [
-    [( 0,  0), (-1,  0), ( 2,  0), (-1,  0), ( 2,  0)],
-    [(-1,  0), ( 0,  0), ( 0,  0), ( 0,  1), ( 0, -2)],
-    [(-1,  1), ( 1,  1), (-2,  1), ( 1,  0), (-2,  0)],
-    [( 0,  1), ( 0,  1), ( 0,  1), ( 0, -1), ( 0,  2)],
-];
-
-static O_OFFSETS: [[(i32, i32); 5]; 4] = [
-    [( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
-    [( 0, -1), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
-    [(-1, -1), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
-    [(-1,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
-];
-
-#[derive(FromPrimitive, PartialEq)]
+// How often a piece shifted straight to the wall by an ARR of zero is moved there again
+const ARR_ZERO_INTERVAL: Duration = Duration::from_millis(16);
+
+// A hard drop bumps the board once, a Tetris shakes it a few times
+const HARD_DROP_SHAKE: Duration = Duration::from_millis(50);
+const TETRIS_SHAKE: Duration = Duration::from_millis(250);
+
+pub type Board = Vec<Vec<Option<Color>>>;
+
+#[derive(Clone, Copy, FromPrimitive, PartialEq)]
 pub enum ShiftDirection { Left, Right, Down }
 
-#[derive(PartialEq)]
-pub enum RotationDirection { Clockwise, CounterClockwise }
+#[derive(Clone, Copy, PartialEq)]
+pub enum RotationDirection { Clockwise, CounterClockwise, Half }
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Spin { None, Mini, Full }
+
+#[derive(Clone, Copy)]
+pub struct LineClear {
+    pub lines: u32,
+    pub spin: Spin,
+    pub perfect: bool,
+    pub combo: i32,
+    pub back_to_back: i32,
+    pub time: Instant,
+}
+
+#[derive(Clone, Copy, EnumString, PartialEq)]
+#[strum(ascii_case_insensitive)]
+pub enum GarbagePattern {
+    #[strum(serialize = "clean")]
+    Clean,
+    #[strum(serialize = "messy")]
+    Messy,
+    #[strum(serialize = "4wide")]
+    FourWide,
+}
+
+// Shapes are defined for the default board, so move them to the given column at the top of this one
+fn spawn_offset(dimension: Dimension, column: i32) -> Dimension {
+    (
+        column - DEFAULT_BOARD_DIMENSION.0 / 2,
+        dimension.1 - DEFAULT_BOARD_DIMENSION.1,
+    )
+}
+
+fn moved(shape: &Shape, offset_x: i32, offset_y: i32) -> Shape {
+    shape.iter().map(|&(x, y)| (x + offset_x, y + offset_y)).collect()
+}
+
+pub fn garbage_color() -> Color {
+    config::theme().garbage
+}
+
+// Hole columns for rows of garbage where consecutive holes never line up
+fn cheese_holes(rng: &mut StdRng, rows: u32, width: usize) -> Vec<Range<usize>> {
+    let mut holes: Vec<Range<usize>> = Vec::new();
+    for _ in 0..rows {
+        let mut hole = rng.gen_range(0..width);
+        while holes.last().is_some_and(|last| last.start == hole) {
+            hole = rng.gen_range(0..width);
+        }
+        holes.push(hole..hole + 1);
+    }
+    holes
+}
+
+// Middle four columns of the board, left open by 4-wide garbage and combo practice
+fn four_wide_well(width: usize) -> Range<usize> {
+    width.saturating_sub(4) / 2..(width + 4) / 2
+}
 
-fn rand_bag_gen() -> Vec<Tetromino> {
+fn garbage_holes(rng: &mut StdRng, pattern: GarbagePattern, rows: u32, width: usize) -> Vec<Range<usize>> {
+    match pattern {
+        GarbagePattern::Clean => {
+            let hole = rng.gen_range(0..width);
+            vec![hole..hole + 1; rows as usize]
+        },
+        GarbagePattern::Messy => (0..rows)
+            .map(|_| rng.gen_range(0..width))
+            .map(|hole| hole..hole + 1)
+            .collect(),
+        GarbagePattern::FourWide => vec![four_wide_well(width); rows as usize],
+    }
+}
+
+// `.` is empty, `X` is garbage and a piece letter is a block in its color
+pub fn parse_board_row(line: &str, width: i32) -> Result<Vec<Option<Color>>, String> {
+    if line.chars().count() != width as usize {
+        return Err(format!("board rows must be {} columns wide", width))
+    }
+    let row = line.chars()
+        .map(|char| match char {
+            '.' => Ok(None),
+            'X' | 'x' => Ok(Some(garbage_color())),
+            _ => TetrominoVariant::from_char(char)
+                .map(|variant| Some(Tetromino::new(variant).color))
+                .ok_or_else(|| format!("invalid block {}", char)),
+        })
+        .collect::<Result<Vec<Option<Color>>, String>>()?;
+    // Full rows would all be cleared at once by the first piece placed
+    if row.iter().all(Option::is_some) {
+        return Err(String::from("board rows must have an empty cell"))
+    }
+    Ok(row)
+}
+
+// Rows are written from top to bottom
+pub fn load_map(path: &str, dimension: Dimension) -> Result<Board, String> {
+    let contents = read_to_string(path).map_err(|err| err.to_string())?;
+
+    let mut board = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue
+        }
+        board.push(parse_board_row(line, dimension.0).map_err(|err| format!("line {}: {}", i + 1, err))?);
+    }
+
+    if board.len() > dimension.1 as usize {
+        return Err(format!("board is taller than {} rows", dimension.1))
+    }
+
+    board.reverse();
+    Ok(board)
+}
+
+fn rand_bag_gen(rng: &mut StdRng) -> Vec<Tetromino> {
     let mut bag = TetrominoVariant::iter()
-        .map(|variant| Tetromino::new(variant))
+        .map(Tetromino::new)
         .collect::<Vec<Tetromino>>();
 
-    bag.shuffle(&mut thread_rng());
+    bag.shuffle(rng);
     bag
 }
 
-pub struct Game {
+// An eighth outcome or a repeat rerolls once among the seven pieces
+fn nes_roll(rng: &mut StdRng, previous: Option<TetrominoVariant>) -> TetrominoVariant {
+    let variants: Vec<TetrominoVariant> = TetrominoVariant::iter().collect();
+    match variants.get(rng.gen_range(0..=variants.len())) {
+        Some(&variant) if Some(variant) != previous => variant,
+        _ => variants[rng.gen_range(0..variants.len())],
+    }
+}
+
+// Pieces are taken from the end of the bag, so they are rolled in order and then reversed
+fn rand_nes_gen(rng: &mut StdRng, mut previous: Option<TetrominoVariant>) -> Vec<Tetromino> {
+    let mut bag: Vec<Tetromino> = (0..TetrominoVariant::iter().len())
+        .map(|_| {
+            let variant = nes_roll(rng, previous);
+            previous = Some(variant);
+            Tetromino::new(variant)
+        })
+        .collect();
+
+    bag.reverse();
+    bag
+}
+
+fn rand_gen(rng: &mut StdRng, randomizer: Randomizer, previous: Option<TetrominoVariant>) -> Vec<Tetromino> {
+    match randomizer {
+        Randomizer::SevenBag => rand_bag_gen(rng),
+        Randomizer::Nes => rand_nes_gen(rng, previous),
+    }
+}
+
+// A falling piece along with the state of the player controlling it
+#[derive(Clone)]
+pub struct Player {
     pub falling: Tetromino,
     pub holding: Option<Tetromino>,
     pub ghost: Option<Tetromino>,
+    pub hint: Option<Shape>,
+    // Board column new pieces are centered on
+    pub spawn_column: i32,
+    pub can_hold: bool,
+    pub last_rotation_kick: Option<(KickTable, usize)>,
+    pub locking: bool,
+    pub lock_reset_count: u8,
+    pub spawning: bool,
+    pub buffered_rotation: Option<RotationDirection>,
+    pub buffered_hold: bool,
+    pub soft_dropping: bool,
+    pub soft_drop_until: Instant,
+    // Key held down to shift the piece and when it next repeats
+    pub held_shift: Option<(KeyCode, ShiftDirection)>,
+    pub shift_repeat_at: Instant,
+    // Rotate and hold keys held down, which are applied to each new piece as it spawns
+    pub held_rotation: Option<(KeyCode, RotationDirection)>,
+    pub held_hold: Option<KeyCode>,
+}
+
+impl Player {
+    fn new(falling: Tetromino, spawn_column: i32) -> Self {
+        Player {
+            falling,
+            holding: None,
+            ghost: None,
+            hint: None,
+            spawn_column,
+            can_hold: true,
+            last_rotation_kick: None,
+            locking: false,
+            lock_reset_count: 0,
+            spawning: false,
+            buffered_rotation: None,
+            buffered_hold: false,
+            soft_dropping: false,
+            soft_drop_until: Instant::now(),
+            held_shift: None,
+            shift_repeat_at: Instant::now(),
+            held_rotation: None,
+            held_hold: None,
+        }
+    }
+}
+
+pub struct Game {
+    pub player: Player,
+    // Second player sharing the board in co-op, who is swapped into `player` while acting
+    pub partner: Option<Player>,
     pub next: Vec<Tetromino>,
     pub bag: Vec<Tetromino>,
+    pub rng: StdRng,
     pub stack: Vec<Vec<Option<Color>>>,
+    pub dimension: Dimension,
+    // Shown above the board in place of the title
+    pub player_name: Option<String>,
+    pub mode: Mode,
+    pub ruleset: Ruleset,
     pub start_level: u32,
     pub score: u32,
     pub level: u32,
     pub lines: u32,
+    pub level_times: Vec<Duration>,
+    pub pieces: u32,
+    pub attack: u32,
+    pub keys: u32,
+    pub garbage_rows: u32,
+    pub garbage_due: Instant,
+    // Start and length of the current screen shake
+    pub shake: Option<(Instant, Duration)>,
+    pub puzzles: Vec<Puzzle>,
+    pub puzzle_index: usize,
+    pub puzzle_lines: u32,
+    pub puzzle_result: Option<bool>,
+    pub chaos_event: Option<ChaosEvent>,
     pub combo: i32,
+    // Consecutive Tetrises and spins after the first, or -1 when the last clear was neither
+    pub back_to_back: i32,
+    pub last_clear: Option<LineClear>,
     pub clearing: HashSet<usize>,
-    pub can_hold: bool,
-    pub locking: bool,
-    pub lock_reset_count: u8,
+    pub clear_start: Instant,
+    pub spin: Spin,
+    // Whether the terminal reports key releases, in which case held keys are tracked instead of
+    // relying on the terminal's key repeat
+    pub key_releases: bool,
+    pub start_time: Instant,
+    pub pause_start: Instant,
+    pub paused_time: Duration,
+    pub end_time: Option<Instant>,
+    pub paused: bool,
+    pub pause_selected: usize,
+    pub settings_open: bool,
+    // Shown in place of the settings title when they couldn't be saved
+    pub settings_notice: Option<String>,
+    pub quit: bool,
+    pub restart: bool,
+    pub complete: bool,
     pub end: bool,
 }
 
 impl Game {
-    pub fn start(start_level: u32) -> Self {
-        let mut bag = rand_bag_gen();
+    pub fn start(start_level: u32, dimension: Dimension, mode: Mode, ruleset: Ruleset, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bag = rand_gen(&mut rng, ruleset.randomizer, None);
+        // Co-op players each start on their own half of the board
+        let spawn_columns = if mode == Mode::Coop {
+            [dimension.0 / 4, dimension.0 * 3 / 4]
+        } else {
+            [dimension.0 / 2; 2]
+        };
         let mut game = Game {
-            falling: bag.pop().unwrap(),
-            holding: None,
-            ghost: None,
+            player: Player::new(bag.pop().unwrap().translated(spawn_offset(dimension, spawn_columns[0])), spawn_columns[0]),
+            partner: None,
             next: bag.split_off(bag.len() - 3),
             bag,
-            stack: vec![vec![None; BOARD_DIMENSION.0 as usize]; BOARD_DIMENSION.1 as usize],
+            stack: vec![vec![None; dimension.0 as usize]; (dimension.1 + BUFFER_HEIGHT) as usize],
+            rng,
+            dimension,
+            player_name: None,
+            mode,
+            ruleset,
             start_level,
             score: 0,
-            level: start_level,
+            // Gravity is only defined from level 1 up
+            level: config::MAX_LEVEL.map_or(start_level, |max_level| start_level.min(max_level)).max(1),
             lines: 0,
+            level_times: Vec::new(),
+            pieces: 0,
+            attack: 0,
+            keys: 0,
+            garbage_rows: 0,
+            garbage_due: Instant::now(),
+            shake: None,
+            puzzles: Vec::new(),
+            puzzle_index: 0,
+            puzzle_lines: 0,
+            puzzle_result: None,
+            chaos_event: None,
             combo: -1,
+            back_to_back: -1,
+            last_clear: None,
             clearing: HashSet::new(),
-            can_hold: true,
-            locking: false,
-            lock_reset_count: 0,
+            clear_start: Instant::now(),
+            spin: Spin::None,
+            key_releases: false,
+            start_time: Instant::now(),
+            pause_start: Instant::now(),
+            paused_time: Duration::ZERO,
+            end_time: None,
+            paused: false,
+            pause_selected: 0,
+            settings_open: false,
+            settings_notice: None,
+            quit: false,
+            restart: false,
+            complete: false,
             end: false,
         };
+        if mode == Mode::Coop {
+            let falling = game.get_next().unwrap().translated(spawn_offset(dimension, spawn_columns[1]));
+            game.partner = Some(Player::new(falling, spawn_columns[1]));
+        }
+        if mode == Mode::Combo {
+            game.fill_walls();
+            let well = four_wide_well(dimension.0 as usize);
+            for column in well.take(3) {
+                game.stack[0][column] = Some(garbage_color());
+            }
+        }
+        if mode == Mode::Cheese {
+            let rows = (*config::CHEESE_ROWS).min((dimension.1 - 4) as u32);
+            let holes = cheese_holes(&mut game.rng, rows, dimension.0 as usize);
+            game.add_garbage(&holes);
+        }
         game.update_ghost();
+        game.with_partner(Game::update_ghost);
         game
     }
 
-    fn get_next(&mut self) -> Tetromino {
-        self.next.push(self.bag.pop().unwrap());
-        if self.bag.is_empty() {
-            self.bag = rand_bag_gen()
+    // Runs `f` with the co-op partner swapped into `player`
+    pub fn with_partner<R>(&mut self, f: impl FnOnce(&mut Game) -> R) -> Option<R> {
+        let mut partner = self.partner.take()?;
+        std::mem::swap(&mut self.player, &mut partner);
+        self.partner = Some(partner);
+        let result = f(self);
+        let mut player = self.partner.take().unwrap();
+        std::mem::swap(&mut self.player, &mut player);
+        self.partner = Some(player);
+        Some(result)
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &Player> {
+        std::iter::once(&self.player).chain(self.partner.as_ref())
+    }
+
+    pub fn partner_is(&mut self, f: impl FnOnce(&mut Game) -> bool) -> bool {
+        self.with_partner(f).unwrap_or(false)
+    }
+
+    // Falling pieces can't move into each other, but only the stack and the floor lock them in place
+    fn blocked(&self, shape: &Shape) -> bool {
+        self.partner.as_ref().is_some_and(|partner| {
+            !partner.spawning && shape.iter().any(|position| partner.falling.shape.contains(position))
+        })
+    }
+
+    fn can_fall(&self) -> bool {
+        !self.hitting_bottom(&self.player.falling) && !self.blocked(&moved(&self.player.falling.shape, 0, -1))
+    }
+
+    // Pushes the falling piece up out of blocks that rose or fell into it
+    fn lift_falling(&mut self) {
+        while self.overlapping(&self.player.falling.shape) && !self.end {
+            self.player.falling = self.player.falling.clone().translated((0, 1));
+            if self.player.falling.shape.iter().any(|position| position.1 >= self.height()) {
+                self.end = true;
+            }
+        }
+        self.update_ghost();
+    }
+
+    // One row for each range of hole columns
+    pub fn add_garbage(&mut self, holes: &[Range<usize>]) {
+        for hole in holes {
+            let mut row = vec![Some(garbage_color()); self.dimension.0 as usize];
+            row[hole.clone()].fill(None);
+            self.stack.insert(0, row);
+            if self.stack.pop().is_some_and(|row| row.iter().any(|block| block.is_some())) {
+                self.end = true;
+            }
+            self.clearing = self.clearing.iter().map(|i| i + 1).collect();
+        }
+        self.garbage_rows += holes.len() as u32;
+
+        self.lift_falling();
+        self.with_partner(Game::lift_falling);
+    }
+
+    pub fn set_puzzles(&mut self, puzzles: Vec<Puzzle>) {
+        self.puzzles = puzzles;
+        self.puzzle_index = 0;
+        self.load_puzzle();
+    }
+
+    pub fn set_board(&mut self, board: &Board) {
+        let mut stack = vec![vec![None; self.dimension.0 as usize]; (self.dimension.1 + BUFFER_HEIGHT) as usize];
+        for (row, blocks) in stack.iter_mut().zip(board.iter()) {
+            row.clone_from(blocks);
+        }
+        self.stack = stack;
+        self.update_ghost();
+    }
+
+    // Sets up the board and piece queue of the current puzzle, discarding whatever was in play
+    fn load_puzzle(&mut self) {
+        let puzzle = self.puzzles[self.puzzle_index].clone();
+
+        self.set_board(&puzzle.board);
+
+        let mut queue: Vec<Tetromino> = puzzle.queue.iter().map(|&variant| Tetromino::new(variant)).collect();
+        let falling = queue.remove(0);
+        self.next = queue.drain(..queue.len().min(3)).collect();
+        queue.reverse();
+        self.bag = queue;
+        self.player.falling = self.spawn_position(falling);
+
+        self.player.holding = None;
+        self.player.can_hold = true;
+        self.player.locking = false;
+        self.player.last_rotation_kick = None;
+        self.player.buffered_rotation = None;
+        self.player.buffered_hold = false;
+        self.clearing.clear();
+        self.combo = -1;
+        self.puzzle_lines = 0;
+        self.puzzle_result = None;
+        self.update_ghost();
+    }
+
+    // Solves the puzzle once its objective is met, or fails it when no pieces are left to place
+    fn check_puzzle(&mut self) {
+        let num_cleared = self.clearing.len() as u32;
+        self.puzzle_lines += num_cleared;
+
+        let perfect_clear = self.stack
+            .iter()
+            .enumerate()
+            .all(|(i, row)| self.clearing.contains(&i) || row.iter().all(|block| block.is_none()));
+
+        let objective = self.puzzles[self.puzzle_index].objective;
+        if objective.met(num_cleared, self.puzzle_lines, self.spin, perfect_clear) {
+            self.puzzle_result = Some(true);
+        } else if self.next.is_empty() && self.player.holding.is_none() {
+            self.puzzle_result = Some(false);
+        }
+    }
+
+    // Moves on to the next puzzle after a solve, or retries the current one after a failure
+    fn finish_puzzle(&mut self, solved: bool) {
+        if solved {
+            self.puzzle_index += 1;
+        }
+        if self.puzzle_index < self.puzzles.len() {
+            self.load_puzzle();
+        }
+        self.player.spawning = false;
+    }
+
+    // Combo practice keeps the columns beside the well filled up to a few rows below the top of the board
+    fn fill_walls(&mut self) {
+        let well = four_wide_well(self.dimension.0 as usize);
+        for row in self.stack.iter_mut().take((self.dimension.1 - 4) as usize) {
+            for (i, block) in row.iter_mut().enumerate() {
+                if !well.contains(&i) && block.is_none() {
+                    *block = Some(garbage_color());
+                }
+            }
+        }
+    }
+
+    // Returns the time until the next call
+    pub fn chaos_tick(&mut self) -> Duration {
+        if self.chaos_event.take().is_some() {
+            return chaos::event_interval(&mut self.rng)
+        }
+
+        let event = ChaosEvent::iter().choose(&mut self.rng).unwrap();
+        if event == ChaosEvent::BonusGarbage {
+            let holes = garbage_holes(&mut self.rng, GarbagePattern::Messy, chaos::BONUS_GARBAGE_ROWS, self.dimension.0 as usize);
+            self.add_garbage(&holes);
+        }
+        self.chaos_event = Some(event);
+        event.duration()
+    }
+
+    pub fn garbage_interval(&self) -> Option<Duration> {
+        self.mode.garbage_interval(self.time_played())
+    }
+
+    // Garbage training inserts a configured batch of garbage on every interval, survival a single messy row
+    fn garbage_batch(&self) -> (GarbagePattern, u32) {
+        match self.mode {
+            Mode::Survival => (GarbagePattern::Messy, 1),
+            _ => (*config::GARBAGE_PATTERN, *config::GARBAGE_LINES),
+        }
+    }
+
+    pub fn pending_garbage(&self) -> Option<u32> {
+        self.garbage_interval().map(|_| self.garbage_batch().1)
+    }
+
+    pub fn rise_garbage(&mut self) {
+        let (pattern, lines) = self.garbage_batch();
+        let holes = garbage_holes(&mut self.rng, pattern, lines, self.dimension.0 as usize);
+        self.add_garbage(&holes);
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if self.paused {
+            self.pause_start = Instant::now();
+            self.pause_selected = 0;
+            self.settings_open = false;
+        } else {
+            self.paused_time += self.pause_start.elapsed();
+        }
+    }
+
+    // Swaps colors of the previous theme for those of the current one after it was reloaded
+    pub fn recolor(&mut self, previous: &Theme) {
+        let theme = config::theme();
+        let recolor = |color: Color| {
+            if color == previous.garbage {
+                theme.garbage
+            } else if let Some(i) = previous.pieces.iter().position(|&piece| piece == color) {
+                theme.pieces[i]
+            } else {
+                color
+            }
+        };
+        for cell in self.stack.iter_mut().flatten().flatten() {
+            *cell = recolor(*cell);
+        }
+        let pieces = [&mut self.player].into_iter()
+            .chain(self.partner.as_mut())
+            .flat_map(|player| [&mut player.falling].into_iter().chain(player.holding.as_mut()).chain(player.ghost.as_mut()))
+            .chain(self.next.iter_mut())
+            .chain(self.bag.iter_mut());
+        for piece in pieces {
+            piece.color = recolor(piece.color);
+        }
+    }
+
+    pub fn time_played(&self) -> Duration {
+        let now = if self.paused {
+            self.pause_start
+        } else {
+            self.end_time.unwrap_or_else(Instant::now)
+        };
+        now.duration_since(self.start_time).saturating_sub(self.paused_time)
+    }
+
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.mode.time_limit().map(|time_limit| time_limit.saturating_sub(self.time_played()))
+    }
+
+    pub fn goal_reached(&self) -> bool {
+        self.mode == Mode::Cheese && self.garbage_rows == 0 ||
+        self.mode == Mode::Puzzle && self.puzzle_index == self.puzzles.len() ||
+        self.mode.line_goal().is_some_and(|line_goal| self.lines >= line_goal) ||
+        self.mode.target_level().is_some_and(|target_level| self.level >= target_level) ||
+        self.time_remaining().is_some_and(|time_remaining| time_remaining.is_zero())
+    }
+
+    pub fn pieces_per_second(&self) -> f32 {
+        let seconds = self.time_played().as_secs_f32();
+        if seconds > 0.0 { self.pieces as f32 / seconds } else { 0.0 }
+    }
+
+    pub fn attacks_per_minute(&self) -> f32 {
+        let minutes = self.time_played().as_secs_f32() / 60.0;
+        if minutes > 0.0 { self.attack as f32 / minutes } else { 0.0 }
+    }
+
+    pub fn keys_per_piece(&self) -> f32 {
+        if self.pieces > 0 { self.keys as f32 / self.pieces as f32 } else { 0.0 }
+    }
+
+    // Puzzles have a fixed queue, so the bag is only refilled in the other modes
+    fn get_next(&mut self) -> Option<Tetromino> {
+        if let Some(tetromino) = self.bag.pop() {
+            self.next.push(tetromino);
+        }
+        if self.bag.is_empty() && self.mode != Mode::Puzzle {
+            let previous = self.next.last().map(|tetromino| tetromino.variant);
+            self.bag = rand_gen(&mut self.rng, self.ruleset.randomizer, previous)
         }
-        self.next.remove(0)
+        if self.next.is_empty() { None } else { Some(self.next.remove(0)) }
+    }
+
+    // Height of the stack including the hidden buffer rows above the visible board
+    fn height(&self) -> i32 {
+        self.stack.len() as i32
     }
 
     fn hitting_bottom(&self, tetromino: &Tetromino) -> bool {
         tetromino.shape.iter().any(|position| {
             position.1 == 0 ||
-            position.1 < BOARD_DIMENSION.1 &&
+            position.1 < self.height() &&
             self.stack[(position.1 - 1) as usize][position.0 as usize].is_some()
         })
     }
@@ -112,32 +645,44 @@ impl Game {
     fn hitting_left(&self, tetromino: &Tetromino) -> bool {
         tetromino.shape.iter().any(|position| {
             position.0 == 0 ||
-            position.1 < BOARD_DIMENSION.1 &&
+            position.1 < self.height() &&
             self.stack[position.1 as usize][(position.0 - 1) as usize].is_some()
-        })
+        }) || self.blocked(&moved(&tetromino.shape, -1, 0))
     }
 
     fn hitting_right(&self, tetromino: &Tetromino) -> bool {
         tetromino.shape.iter().any(|position| {
-            position.0 == BOARD_DIMENSION.0 - 1 ||
-            position.1 < BOARD_DIMENSION.1 &&
+            position.0 == self.dimension.0 - 1 ||
+            position.1 < self.height() &&
             self.stack[position.1 as usize][(position.0 + 1) as usize].is_some()
-        })
+        }) || self.blocked(&moved(&tetromino.shape, 1, 0))
     }
 
-    fn update_ghost(&mut self) {
-        let mut ghost = self.falling.clone();
+    pub fn toggle_hint(&mut self) {
+        let show_hint = config::settings().show_hint;
+        config::settings_mut().show_hint = !show_hint;
+        self.update_ghost();
+    }
+
+    // The hint only depends on the falling piece and the stack, so it is kept up to date with the ghost
+    pub fn update_ghost(&mut self) {
+        self.player.hint = if config::settings().show_hint { best_placement(&self.stack, &self.player.falling) } else { None };
+
+        if !self.ruleset.ghost {
+            return
+        }
+        let mut ghost = self.player.falling.clone();
         while !self.hitting_bottom(&ghost) {
             for position in ghost.shape.iter_mut() {
                 position.1 -= 1;
             }
         }
-        self.ghost = if self.overlapping(&ghost.shape) { None } else { Some(ghost) };
+        self.player.ghost = if self.overlapping(&ghost.shape) { None } else { Some(ghost) };
     }
 
     fn reset_lock_timer(&mut self, lock_delay: &mut Pin<&mut Sleep>) {
-        if self.lock_reset_count < LOCK_RESET_LIMIT {
-            lock_delay.set(sleep(LOCK_DURATION));
+        if self.player.lock_reset_count < LOCK_RESET_LIMIT {
+            lock_delay.set(sleep(self.speed_curve().lock_duration(self.level)));
         }
     }
 
@@ -147,41 +692,44 @@ impl Game {
         lock_delay: &mut Pin<&mut Sleep>,
         line_clear_delay: &mut Pin<&mut Sleep>,
     ) {
-        if self.lock_reset_count == LOCK_RESET_LIMIT {
+        if self.player.lock_reset_count == LOCK_RESET_LIMIT {
             self.place(line_clear_delay)
         }
 
         match direction {
             ShiftDirection::Left => {
-                if !self.hitting_left(&self.falling) {
-                    for position in self.falling.shape.iter_mut() {
+                if !self.hitting_left(&self.player.falling) {
+                    for position in self.player.falling.shape.iter_mut() {
                         position.0 -= 1;
                     }
-                    self.falling.center.0 -= 1;
-                    self.lock_reset_count += 1;
+                    self.player.falling.center.0 -= 1;
+                    self.player.last_rotation_kick = None;
+                    self.player.lock_reset_count += 1;
                     self.reset_lock_timer(lock_delay);
                 }
             },
             ShiftDirection::Right => {
-                if !self.hitting_right(&self.falling) {
-                    for position in self.falling.shape.iter_mut() {
+                if !self.hitting_right(&self.player.falling) {
+                    for position in self.player.falling.shape.iter_mut() {
                         position.0 += 1;
                     }
-                    self.falling.center.0 += 1;
-                    self.lock_reset_count += 1;
+                    self.player.falling.center.0 += 1;
+                    self.player.last_rotation_kick = None;
+                    self.player.lock_reset_count += 1;
                     self.reset_lock_timer(lock_delay);
                 }
             },
             ShiftDirection::Down => {
-                if !self.hitting_bottom(&self.falling) {
-                    for position in self.falling.shape.iter_mut() {
+                if self.can_fall() {
+                    for position in self.player.falling.shape.iter_mut() {
                         position.1 -= 1;
                     }
-                    self.falling.center.1 -= 1;
-                    self.lock_reset_count = 0;
+                    self.player.falling.center.1 -= 1;
+                    self.player.last_rotation_kick = None;
+                    self.player.lock_reset_count = 0;
                     self.reset_lock_timer(lock_delay);
                 }
-                self.locking = self.hitting_bottom(&self.falling);
+                self.player.locking = self.hitting_bottom(&self.player.falling);
             },
         }
 
@@ -189,65 +737,69 @@ impl Game {
     }
 
     fn overlapping(&self, shape: &Shape) -> bool {
+        self.overlapping_stack(shape) || self.blocked(shape)
+    }
+
+    fn overlapping_stack(&self, shape: &Shape) -> bool {
         shape.iter().any(|position| {
             position.0 < 0 ||
             position.1 < 0 ||
-            position.0 > BOARD_DIMENSION.0 - 1 ||
-            position.1 > BOARD_DIMENSION.1 - 1 ||
+            position.0 > self.dimension.0 - 1 ||
+            position.1 > self.height() - 1 ||
             self.stack[position.1 as usize][position.0 as usize].is_some()
         })
     }
 
     pub fn rotate(&mut self, direction: RotationDirection, lock_delay: &mut Pin<&mut Sleep>) {
+        if self.try_rotate(direction) {
+            self.player.lock_reset_count += 1;
+            self.reset_lock_timer(lock_delay);
+        }
+    }
+
+    fn try_rotate(&mut self, direction: RotationDirection) -> bool {
         let (angle, new_direction) = match direction {
             RotationDirection::Clockwise => (
-                f32::from(-90.0).to_radians(),
-                CardinalDirection::from_i32((self.falling.direction as i32 + 1) % 4).unwrap(),
+                (-90.0f32).to_radians(),
+                CardinalDirection::from_i32((self.player.falling.direction as i32 + 1) % 4).unwrap(),
             ),
             RotationDirection::CounterClockwise => (
-                f32::from(90.0).to_radians(),
-                CardinalDirection::from_i32(((self.falling.direction as i32 - 1) % 4 + 4) % 4).unwrap(),
+                90.0f32.to_radians(),
+                CardinalDirection::from_i32(((self.player.falling.direction as i32 - 1) % 4 + 4) % 4).unwrap(),
+            ),
+            RotationDirection::Half => (
+                180.0f32.to_radians(),
+                CardinalDirection::from_i32((self.player.falling.direction as i32 + 2) % 4).unwrap(),
             ),
         };
 
-        let rotated: Vec<(i32, i32)> = self.falling.shape.iter().map(|&(x, y)| {
-            let x = (x - self.falling.center.0) as f32;
-            let y = (y - self.falling.center.1) as f32;
+        let rotated: Vec<(i32, i32)> = self.player.falling.shape.iter().map(|&(x, y)| {
+            let x = (x - self.player.falling.center.0) as f32;
+            let y = (y - self.player.falling.center.1) as f32;
             (
-                ((x * angle.cos() - y * angle.sin()) + self.falling.center.0 as f32).round() as i32,
-                ((x * angle.sin() + y * angle.cos()) + self.falling.center.1 as f32).round() as i32,
+                ((x * angle.cos() - y * angle.sin()) + self.player.falling.center.0 as f32).round() as i32,
+                ((x * angle.sin() + y * angle.cos()) + self.player.falling.center.1 as f32).round() as i32,
             )
         }).collect();
 
-        let offset_table = match self.falling.variant {
-            TetrominoVariant::J |
-            TetrominoVariant::L |
-            TetrominoVariant::S |
-            TetrominoVariant::T |
-            TetrominoVariant::Z => JLSTZ_OFFSETS,
-            TetrominoVariant::I => I_OFFSETS,
-            TetrominoVariant::O => O_OFFSETS,
-        };
+        let kicks = config::ROTATION_SYSTEM.kicks(self.player.falling.variant, self.player.falling.direction, new_direction);
+        let kick_table = config::ROTATION_SYSTEM.kick_table(self.player.falling.direction, new_direction);
 
-        for i in 0..offset_table[0].len() {
-            let offset_x = offset_table[new_direction as usize][i].0
-                - offset_table[self.falling.direction as usize][i].0;
-            let offset_y = offset_table[new_direction as usize][i].1
-                - offset_table[self.falling.direction as usize][i].1;
-
-            let kicked = rotated.iter().map(|&(x, y)| (x - offset_x, y - offset_y)).collect();
+        for (i, (kick_x, kick_y)) in kicks.into_iter().enumerate() {
+            let kicked = rotated.iter().map(|&(x, y)| (x + kick_x, y + kick_y)).collect();
 
             if !self.overlapping(&kicked) {
-                self.falling.shape = kicked;
-                self.falling.center.0 -= offset_x;
-                self.falling.center.1 -= offset_y;
-                self.falling.direction = new_direction;
-                self.lock_reset_count += 1;
+                self.player.falling.shape = kicked;
+                self.player.falling.center.0 += kick_x;
+                self.player.falling.center.1 += kick_y;
+                self.player.falling.direction = new_direction;
+                self.player.last_rotation_kick = Some((kick_table, i));
                 self.update_ghost();
-                self.reset_lock_timer(lock_delay);
-                return
+                return true
             }
         }
+
+        false
     }
 
     fn mark_clear(&mut self) {
@@ -261,24 +813,54 @@ impl Game {
     }
 
     pub fn line_clear(&mut self) {
-        let stack = replace(&mut self.stack, Vec::new());
+        let stack = take(&mut self.stack);
 
         for (i, row) in stack.into_iter().enumerate() {
-            if self.clearing.get(&i).is_none() {
+            if !self.clearing.contains(&i) {
                 self.stack.push(row);
             }
         }
 
         let num_cleared = self.clearing.len() as u32;
 
-        self.stack.extend(vec![vec![None; BOARD_DIMENSION.0 as usize]; num_cleared as usize]);
+        let garbage_cleared = self.clearing.iter().filter(|&&i| i < self.garbage_rows as usize).count();
+        self.garbage_rows -= garbage_cleared as u32;
+
+        self.stack.extend(vec![vec![None; self.dimension.0 as usize]; num_cleared as usize]);
+
+        if self.mode == Mode::Combo {
+            self.fill_walls();
+        }
 
         if num_cleared > 0 {
             self.lines += num_cleared;
-            self.level = self.start_level + self.lines / 10;
+            if self.mode != Mode::Zen {
+                self.level = self.start_level + self.lines / 10;
+            }
+            if let Some(max_level) = *config::MAX_LEVEL {
+                self.level = self.level.min(max_level);
+            }
+            self.level = self.level.max(1);
+            while (self.level_times.len() as u32) < self.level.saturating_sub(self.start_level) {
+                self.level_times.push(self.time_played());
+            }
             self.combo += 1;
             self.calc_score(num_cleared);
-            self.update_ghost();
+            self.calc_attack(num_cleared);
+            self.back_to_back = if num_cleared == 4 || self.spin != Spin::None { self.back_to_back + 1 } else { -1 };
+            self.last_clear = Some(LineClear {
+                lines: num_cleared,
+                spin: self.spin,
+                perfect: self.perfect_clear(),
+                combo: self.combo,
+                back_to_back: self.back_to_back,
+                time: Instant::now(),
+            });
+            if num_cleared == 4 {
+                self.start_shake(TETRIS_SHAKE);
+            }
+            self.lift_falling();
+            self.with_partner(Game::lift_falling);
         } else {
             self.combo = -1;
         }
@@ -286,89 +868,467 @@ impl Game {
         self.clearing.clear();
     }
 
+    fn occupied(&self, position: Dimension) -> bool {
+        self.overlapping(&vec![position])
+    }
+
+    fn immobile(&self) -> bool {
+        [(-1, 0), (1, 0), (0, 1)].iter().all(|&(offset_x, offset_y)| {
+            self.overlapping(&self.player.falling.shape
+                .iter()
+                .map(|&(x, y)| (x + offset_x, y + offset_y))
+                .collect())
+        })
+    }
+
+    fn detect_spin(&self) -> Spin {
+        if self.player.last_rotation_kick.is_none() {
+            return Spin::None
+        }
+
+        if self.player.falling.variant != TetrominoVariant::T {
+            return if *config::ALL_SPIN && self.immobile() { Spin::Mini } else { Spin::None }
+        }
+
+        let (x, y) = self.player.falling.center;
+        let front_corners = match self.player.falling.direction {
+            CardinalDirection::North => [(x - 1, y + 1), (x + 1, y + 1)],
+            CardinalDirection::East  => [(x + 1, y + 1), (x + 1, y - 1)],
+            CardinalDirection::South => [(x - 1, y - 1), (x + 1, y - 1)],
+            CardinalDirection::West  => [(x - 1, y + 1), (x - 1, y - 1)],
+        };
+        let corners = [(x - 1, y + 1), (x + 1, y + 1), (x - 1, y - 1), (x + 1, y - 1)];
+
+        let num_corners = corners.iter().filter(|&&corner| self.occupied(corner)).count();
+        let num_front_corners = front_corners.iter().filter(|&&corner| self.occupied(corner)).count();
+
+        // The last kick of a quarter turn under SRS upgrades a mini T-spin to a full one
+        if num_corners < 3 {
+            Spin::None
+        } else if num_front_corners == 2 || self.player.last_rotation_kick == Some((KickTable::SrsQuarterTurn, 4)) {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    pub fn line_clear_progress(&self) -> f32 {
+        (self.clear_start.elapsed().as_secs_f32() / LINE_CLEAR_DURATION.as_secs_f32()).min(1.0)
+    }
+
+    // More than four rows can clear at once when a piece locks during another clear, which scores as
+    // a tetris
     fn calc_score(&mut self, num_cleared: u32) {
-        let full_clear = self.stack.iter().flatten().all(|block| block.is_none());
+        let num_cleared = num_cleared.min(4);
+        if !self.ruleset.bonuses {
+            self.score += self.level * self.ruleset.line_clear_points[num_cleared as usize - 1];
+            return
+        }
+
+        let full_clear = self.perfect_clear();
         self.score += if full_clear {
             match num_cleared {
                 1 => self.level * 800,
                 2 => self.level * 1200,
                 3 => self.level * 1800,
-                4 => self.level * 2000,
+                _ => self.level * 2000,
+            }
+        } else if self.spin == Spin::Full {
+            match num_cleared {
+                1 => self.level * 800,
+                2 => self.level * 1200,
+                3 => self.level * 1600,
                 _ => 0,
             }
-        } else {
+        } else if self.spin == Spin::Mini {
             match num_cleared {
-                1 => self.level * 100,
-                2 => self.level * 300,
-                3 => self.level * 500,
-                4 => self.level * 800,
+                1 => self.level * 200,
+                2 => self.level * 400,
                 _ => 0,
             }
+        } else {
+            self.level * self.ruleset.line_clear_points[num_cleared as usize - 1]
         };
         self.score += 50 * self.combo as u32 * self.level;
     }
 
+    fn perfect_clear(&self) -> bool {
+        self.stack.iter().flatten().all(|block| block.is_none())
+    }
+
+    // Lines of garbage the clear would send to an opponent under the guideline attack table
+    fn calc_attack(&mut self, num_cleared: u32) {
+        let full_clear = self.perfect_clear();
+        self.attack += if full_clear {
+            10
+        } else {
+            match (self.spin, num_cleared) {
+                (Spin::Full, _) => num_cleared * 2,
+                (_, 4) => 4,
+                _ => num_cleared - 1,
+            }
+        };
+    }
+
     pub fn place(&mut self, line_clear_delay: &mut Pin<&mut Sleep>) {
-        if !self.hitting_bottom(&self.falling) {
+        // The piece was moved off the ledge it was locking on, so it falls again instead of firing
+        // the lock timer over and over
+        if !self.hitting_bottom(&self.player.falling) {
+            self.player.locking = false;
             return
         }
 
-        for position in self.falling.shape.iter() {
-            if position.1 > BOARD_DIMENSION.1 - 1 {
-                self.end = true;
-                return
-            }
-            self.stack[position.1 as usize][position.0 as usize] = Some(self.falling.color);
+        // Lock out: the piece locked entirely above the visible part of the board
+        let locked_out = self.player.falling.shape.iter().all(|position| position.1 > self.dimension.1 - 1);
+        if locked_out && self.mode != Mode::Zen {
+            self.end = true;
+            return
+        }
+
+        self.spin = if self.ruleset.bonuses { self.detect_spin() } else { Spin::None };
+        self.pieces += 1;
+
+        for position in self.player.falling.shape.iter() {
+            self.stack[position.1 as usize][position.0 as usize] = Some(self.player.falling.color);
+        }
+
+        if locked_out {
+            self.top_out();
         }
 
         self.mark_clear();
+        self.clear_start = Instant::now();
+
+        if self.mode == Mode::Puzzle {
+            self.check_puzzle();
+        }
+
+        if self.clearing.is_empty() {
+            self.score += match self.spin {
+                Spin::Full => self.level * 400,
+                Spin::Mini => self.level * 100,
+                Spin::None => 0,
+            };
+        }
 
-        let mut falling = self.get_next();
-        for i in 17..20 {
+        // A finished puzzle waits for its line clear to play out before the next one is loaded
+        self.player.spawning = true;
+        self.player.ghost = None;
+        if !config::ENTRY_DELAY.is_zero() || self.puzzle_result.is_some() {
+            line_clear_delay.set(sleep(LINE_CLEAR_DURATION.max(*config::ENTRY_DELAY)));
+        } else {
+            self.spawn();
+            line_clear_delay.set(sleep(LINE_CLEAR_DURATION));
+        }
+
+        self.with_partner(Game::update_ghost);
+    }
+
+    // Zen mode never ends, instead the top half of the board is cleared to make room
+    fn top_out(&mut self) {
+        if self.mode != Mode::Zen {
+            self.end = true;
+            return
+        }
+        for row in self.stack.iter_mut().skip((self.dimension.1 / 2) as usize) {
+            row.fill(None);
+        }
+        self.update_ghost();
+        self.with_partner(Game::update_ghost);
+    }
+
+    // Moves a new piece to the top of the board, raising it into the buffer rows when the stack is high
+    fn spawn_position(&self, tetromino: Tetromino) -> Tetromino {
+        let mut tetromino = tetromino.translated(spawn_offset(self.dimension, self.player.spawn_column));
+        for i in (self.dimension.1 - 3) as usize..self.dimension.1 as usize {
             if self.stack[i].iter().any(|block| block.is_some()) {
-                for position in falling.shape.iter_mut() {
-                    position.1 += 1;
-                }
-                falling.center.1 += 1;
+                tetromino = tetromino.translated((0, 1));
             }
         }
+        tetromino
+    }
 
-        self.falling = falling;
-        self.locking = false;
-        self.can_hold = true;
+    pub fn spawn(&mut self) {
+        if let Some(solved) = self.puzzle_result.take() {
+            self.finish_puzzle(solved);
+            return
+        }
+
+        // The spawn waits while the other co-op piece is in the way
+        let upcoming = self.next.first().or(self.bag.last()).or(self.player.holding.as_ref());
+        if upcoming.is_some_and(|tetromino| self.blocked(&self.spawn_position(tetromino.clone()).shape)) {
+            return
+        }
+
+        // The last piece of a puzzle may be sitting in hold once the queue runs out
+        let falling = self.get_next().or_else(|| self.player.holding.take()).unwrap();
+        self.player.falling = self.spawn_position(falling);
+        self.player.locking = false;
+        self.player.can_hold = true;
+        self.player.last_rotation_kick = None;
+        self.player.spawning = false;
+
+        if take(&mut self.player.buffered_hold) || self.player.held_hold.is_some() {
+            self.hold();
+        }
+        if let Some(direction) = self.player.buffered_rotation.take().or(self.player.held_rotation.map(|(_, direction)| direction)) {
+            self.try_rotate(direction);
+        }
+
+        // Block out: the new piece spawned overlapping the stack
+        if self.overlapping_stack(&self.player.falling.shape) {
+            self.top_out();
+        }
 
         self.update_ghost();
+    }
+
+    // Most terminals only report key presses, so soft drop stays active as long as the key keeps
+    // repeating unless its release is reported
+    pub fn soft_drop(&mut self) {
+        self.player.soft_dropping = true;
+        self.player.soft_drop_until = Instant::now() + SOFT_DROP_HOLD_DURATION;
+    }
+
+    // Pressing a shift key charges DAS from scratch, so pressing the other direction while one is
+    // held cancels the charge and takes over
+    pub fn hold_shift(&mut self, code: KeyCode, direction: ShiftDirection) {
+        self.player.held_shift = Some((code, direction));
+        self.player.shift_repeat_at = Instant::now() + config::settings().das;
+    }
+
+    pub fn release_key(&mut self, key: Key, action: Option<Action>) {
+        if self.player.held_shift.is_some_and(|(held, _)| held == key.code) {
+            self.player.held_shift = None;
+        }
+        if self.player.held_rotation.is_some_and(|(held, _)| held == key.code) {
+            self.player.held_rotation = None;
+        }
+        if self.player.held_hold == Some(key.code) {
+            self.player.held_hold = None;
+        }
+        if action == Some(Action::SoftDrop) {
+            self.player.soft_dropping = false;
+        }
+    }
 
-        line_clear_delay.set(sleep(LINE_CLEAR_DURATION));
+    // Repeats the held shift every ARR once DAS has charged, where an ARR of zero moves the piece
+    // straight to the wall
+    pub fn auto_shift(&mut self, lock_delay: &mut Pin<&mut Sleep>, line_clear_delay: &mut Pin<&mut Sleep>) {
+        let Some((_, direction)) = self.player.held_shift else {
+            return
+        };
+        let arr = config::settings().arr;
+        let repeats = if arr.is_zero() { self.dimension.0 } else { 1 };
+        for _ in 0..repeats {
+            if self.player.spawning {
+                break
+            }
+            self.shift(direction, lock_delay, line_clear_delay);
+        }
+        self.player.shift_repeat_at = Instant::now() + arr.max(ARR_ZERO_INTERVAL);
     }
 
-    pub fn soft_drop(&mut self, lock_delay: &mut Pin<&mut Sleep>, line_clear_delay: &mut Pin<&mut Sleep>) {
+    pub fn fall(&mut self, lock_delay: &mut Pin<&mut Sleep>, line_clear_delay: &mut Pin<&mut Sleep>) {
         self.shift(ShiftDirection::Down, lock_delay, line_clear_delay);
-        if !self.hitting_bottom(&self.falling) {
+        if self.player.soft_dropping && self.can_fall() {
             self.score += 1;
         }
     }
 
+    pub fn drop_duration(&self) -> Duration {
+        let mut drop_duration = self.speed_curve().drop_duration(self.level);
+        if self.chaos_event == Some(ChaosEvent::SpeedBurst) {
+            drop_duration = drop_duration.div_f32(chaos::SPEED_BURST_FACTOR);
+        }
+        if self.player.soft_dropping {
+            drop_duration.div_f32(*config::SOFT_DROP_FACTOR)
+        } else {
+            drop_duration
+        }
+    }
+
+    fn drop_to_stack(&mut self, lock_delay: &mut Pin<&mut Sleep>) -> u32 {
+        let mut rows = 0;
+        while self.can_fall() {
+            for position in self.player.falling.shape.iter_mut() {
+                position.1 -= 1;
+            }
+            self.player.falling.center.1 -= 1;
+            rows += 1;
+        }
+        if rows > 0 {
+            self.player.last_rotation_kick = None;
+            // A piece resting on the other co-op piece keeps falling once it moves away
+            self.player.locking = self.hitting_bottom(&self.player.falling);
+            self.player.lock_reset_count = 0;
+            self.reset_lock_timer(lock_delay);
+        }
+        rows
+    }
+
+    pub fn sonic_drop(&mut self, lock_delay: &mut Pin<&mut Sleep>) {
+        self.score += self.drop_to_stack(lock_delay);
+    }
+
+    fn speed_curve(&self) -> SpeedCurve {
+        self.ruleset.speed_curve.unwrap_or(*config::SPEED_CURVE)
+    }
+
+    pub fn twenty_g(&self) -> bool {
+        self.drop_duration().is_zero()
+    }
+
+    pub fn gravity_pending(&self) -> bool {
+        self.twenty_g() && !self.player.spawning && !self.paused && self.can_fall()
+    }
+
+    pub fn gravity_drop(&mut self, lock_delay: &mut Pin<&mut Sleep>) {
+        let rows = self.drop_to_stack(lock_delay);
+        if self.player.soft_dropping {
+            self.score += rows;
+        }
+    }
+
     pub fn hard_drop(&mut self, line_clear_delay: &mut Pin<&mut Sleep>) {
-        while !self.hitting_bottom(&self.falling) {
-            for position in self.falling.shape.iter_mut() {
+        while self.can_fall() {
+            for position in self.player.falling.shape.iter_mut() {
                 position.1 -= 1;
                 self.score += 2;
             }
+            self.player.falling.center.1 -= 1;
+            self.player.last_rotation_kick = None;
         }
+        self.start_shake(HARD_DROP_SHAKE);
         self.place(line_clear_delay);
     }
 
+    fn start_shake(&mut self, duration: Duration) {
+        if *config::SCREEN_SHAKE {
+            self.shake = Some((Instant::now(), duration));
+        }
+    }
+
     pub fn hold(&mut self) {
-        if self.can_hold {
-            let swap = self.holding.clone().unwrap_or_else(|| self.get_next());
+        if self.player.can_hold && self.ruleset.hold {
+            // Holding is turned down while the other co-op piece is where the swapped piece would go
+            let upcoming = self.player.holding.as_ref().or(self.next.first()).or(self.bag.last());
+            if upcoming.is_some_and(|tetromino| self.blocked(&self.spawn_position(tetromino.clone()).shape)) {
+                return
+            }
+            let Some(swap) = self.player.holding.clone().or_else(|| self.get_next()) else {
+                return
+            };
+
+            self.player.holding = Some(Tetromino::new(self.player.falling.variant));
+            self.player.falling = self.spawn_position(swap);
+            self.player.can_hold = false;
+            self.player.last_rotation_kick = None;
 
-            self.holding = Some(Tetromino::new(self.falling.variant));
-            self.falling = swap;
-            self.can_hold = false;
+            if self.overlapping_stack(&self.player.falling.shape) {
+                self.top_out();
+            }
 
             self.update_ghost();
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::write};
+
+    use crate::ruleset::{GUIDELINE, NES};
+
+    use super::*;
+
+    #[test]
+    fn nes_randomizer_rerolls_repeats() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut previous = None;
+        let mut repeats = 0;
+        for _ in 0..7000 {
+            let variant = nes_roll(&mut rng, previous);
+            repeats += (Some(variant) == previous) as u32;
+            previous = Some(variant);
+        }
+        // Without the reroll about one piece in seven would repeat, with it about one in 28
+        assert!((150..400).contains(&repeats), "{} repeats", repeats);
+    }
+
+    #[test]
+    fn parses_board_rows() {
+        let row = parse_board_row("XX..IIII.T", 10).unwrap();
+        assert_eq!(row.len(), 10);
+        assert_eq!(row[0], Some(garbage_color()));
+        assert_eq!(row[2], None);
+        assert_eq!(row[4], Some(Tetromino::new(TetrominoVariant::I).color));
+    }
+
+    #[test]
+    fn rejects_invalid_board_rows() {
+        assert_eq!(parse_board_row("XX..", 10), Err(String::from("board rows must be 10 columns wide")));
+        assert_eq!(parse_board_row("XX..?.....", 10), Err(String::from("invalid block ?")));
+        assert_eq!(parse_board_row("XXXXXXXXXX", 10), Err(String::from("board rows must have an empty cell")));
+    }
+
+    #[test]
+    fn rejects_maps_with_full_rows() {
+        let path = temp_dir().join(format!("tetris_full_rows_{}.txt", std::process::id()));
+        write(&path, "XXXXXXXXXX\n".repeat(5)).unwrap();
+        let map = load_map(path.to_str().unwrap(), (10, 20));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(map.err(), Some(String::from("line 1: board rows must have an empty cell")));
+    }
+
+    #[test]
+    fn scores_more_than_four_rows() {
+        for ruleset in [GUIDELINE, NES] {
+            let mut game = Game::start(1, (10, 20), Mode::Endless, ruleset, 0);
+            game.stack[0][0] = Some(garbage_color());
+            game.combo = 0;
+            game.calc_score(5);
+            assert_eq!(game.score, ruleset.line_clear_points[3]);
+        }
+    }
+
+    #[test]
+    fn coop_spawn_waits_for_partner() {
+        let mut game = Game::start(1, (20, 20), Mode::Coop, GUIDELINE, 0);
+        let upcoming = game.next[0].clone();
+        let partner = game.partner.as_mut().unwrap();
+        partner.falling = upcoming.translated(spawn_offset((20, 20), game.player.spawn_column));
+        game.player.spawning = true;
+        game.spawn();
+        assert!(game.player.spawning && !game.end);
+
+        game.partner.as_mut().unwrap().spawning = true;
+        game.spawn();
+        assert!(!game.player.spawning && !game.end);
+    }
+
+    #[test]
+    fn keeps_level_above_zero() {
+        let mut game = Game::start(0, (10, 20), Mode::Endless, GUIDELINE, 0);
+        game.stack[0].fill(Some(garbage_color()));
+        game.clearing.insert(0);
+        game.line_clear();
+        assert_eq!(game.level, 1);
+    }
+
+    #[test]
+    fn only_srs_quarter_turn_kicks_upgrade_t_spins() {
+        let mut game = Game::start(1, (10, 20), Mode::Endless, GUIDELINE, 0);
+        game.player.falling = Tetromino::new(TetrominoVariant::T);
+        let (x, y) = game.player.falling.center;
+        for (corner_x, corner_y) in [(x - 1, y - 1), (x + 1, y - 1), (x - 1, y + 1)] {
+            game.stack[corner_y as usize][corner_x as usize] = Some(garbage_color());
+        }
+
+        game.player.last_rotation_kick = Some((KickTable::SrsHalfTurn, 4));
+        assert!(game.detect_spin() == Spin::Mini);
+        game.player.last_rotation_kick = Some((KickTable::SrsQuarterTurn, 4));
+        assert!(game.detect_spin() == Spin::Full);
+    }
+}
@@ -0,0 +1,78 @@
+use core::time::Duration;
+use strum_macros::EnumString;
+
+use crate::config;
+
+const FRAME_RATE: f32 = 60.0;
+const TWENTY_G: f32 = 20.0;
+
+const LOCK_DURATION: Duration = Duration::from_millis(500);
+
+// Gravity in 1/256ths of a row per frame, from level 1 up to the point where it becomes 20G
+static MASTER_GRAVITY: [u32; 10] = [4, 8, 12, 16, 32, 64, 128, 256, 512, 1024];
+
+// Frames to fall one row on the NES from level 1 (level 0 there), the last value repeats
+static NES_FRAMES: [u32; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 1,
+];
+
+// Lock delay in frames for each level after reaching 20G, the last value repeats
+static MASTER_LOCK_FRAMES: [u32; 5] = [30, 27, 24, 20, 17];
+
+#[derive(Clone, Copy, EnumString, PartialEq)]
+#[strum(ascii_case_insensitive)]
+pub enum SpeedCurve {
+    #[strum(serialize = "guideline")]
+    Guideline,
+    #[strum(serialize = "master")]
+    Master,
+    #[strum(serialize = "20g")]
+    TwentyG,
+    #[strum(serialize = "nes")]
+    Nes,
+}
+
+fn rows_per_frame(level: u32, curve: SpeedCurve) -> f32 {
+    if let Some(gravity_table) = &*config::GRAVITY_TABLE {
+        let index = (level as usize - 1).min(gravity_table.len() - 1);
+        return 1.0 / gravity_table[index]
+    }
+
+    match curve {
+        SpeedCurve::Guideline => {
+            let drop_rate = (0.8 - (level - 1) as f32 * 0.007).powf((level - 1) as f32);
+            1.0 / (drop_rate * FRAME_RATE)
+        },
+        SpeedCurve::Master => MASTER_GRAVITY
+            .get(level as usize - 1)
+            .map_or(TWENTY_G, |&gravity| gravity as f32 / 256.0),
+        SpeedCurve::TwentyG => TWENTY_G,
+        SpeedCurve::Nes => 1.0 / NES_FRAMES[(level as usize - 1).min(NES_FRAMES.len() - 1)] as f32,
+    }
+}
+
+impl SpeedCurve {
+    // Zero when pieces fall instantly (20G)
+    pub fn drop_duration(&self, level: u32) -> Duration {
+        let rows_per_frame = rows_per_frame(level, *self);
+        if !rows_per_frame.is_finite() || rows_per_frame >= TWENTY_G {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f32(1.0 / (rows_per_frame * FRAME_RATE))
+        }
+    }
+
+    pub fn lock_duration(&self, level: u32) -> Duration {
+        match self {
+            SpeedCurve::Master => {
+                let index = (level as usize).saturating_sub(MASTER_GRAVITY.len() + 1);
+                let frames = MASTER_LOCK_FRAMES[index.min(MASTER_LOCK_FRAMES.len() - 1)];
+                Duration::from_secs_f32(frames as f32 / FRAME_RATE)
+            },
+            // Pieces lock as soon as the next gravity step fails to move them down
+            SpeedCurve::Nes => self.drop_duration(level),
+            _ => LOCK_DURATION,
+        }
+    }
+}
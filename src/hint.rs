@@ -0,0 +1,102 @@
+use crate::{game::Board, tetromino::{Shape, Tetromino}};
+
+// Weights for the stack features of a placement, as found by Yiyuan Lee's genetic search
+const HEIGHT_WEIGHT: f32 = -0.510066;
+const LINES_WEIGHT: f32 = 0.760666;
+const HOLES_WEIGHT: f32 = -0.35663;
+const BUMPINESS_WEIGHT: f32 = -0.184483;
+
+// The four orientations of a shape moved to the origin, with duplicates removed
+fn orientations(tetromino: &Tetromino) -> Vec<Shape> {
+    let mut orientations: Vec<Shape> = Vec::new();
+    let mut shape = tetromino.shape.clone();
+    for _ in 0..4 {
+        let min_x = shape.iter().map(|position| position.0).min().unwrap();
+        let min_y = shape.iter().map(|position| position.1).min().unwrap();
+        let mut normalized: Shape = shape.iter().map(|&(x, y)| (x - min_x, y - min_y)).collect();
+        normalized.sort();
+        if !orientations.contains(&normalized) {
+            orientations.push(normalized);
+        }
+        shape = shape.iter().map(|&(x, y)| (y, -x)).collect();
+    }
+    orientations
+}
+
+fn fits(stack: &Board, shape: &Shape) -> bool {
+    shape.iter().all(|&(x, y)| {
+        x >= 0 &&
+        y >= 0 &&
+        (x as usize) < stack[0].len() &&
+        (y as usize) < stack.len() &&
+        stack[y as usize][x as usize].is_none()
+    })
+}
+
+// Scores the stack left after locking `shape` and clearing any full rows
+fn evaluate(stack: &Board, shape: &Shape) -> f32 {
+    let mut filled: Vec<Vec<bool>> = stack
+        .iter()
+        .map(|row| row.iter().map(|block| block.is_some()).collect())
+        .collect();
+    for &(x, y) in shape {
+        filled[y as usize][x as usize] = true;
+    }
+
+    let height = filled.len();
+    filled.retain(|row| !row.iter().all(|&block| block));
+    let lines = height - filled.len();
+
+    let heights: Vec<usize> = (0..stack[0].len())
+        .map(|x| filled.iter().rposition(|row| row[x]).map_or(0, |y| y + 1))
+        .collect();
+
+    let holes = heights
+        .iter()
+        .enumerate()
+        .map(|(x, &column_height)| (0..column_height).filter(|&y| !filled[y][x]).count())
+        .sum::<usize>();
+
+    let bumpiness = heights
+        .windows(2)
+        .map(|pair| pair[0].abs_diff(pair[1]))
+        .sum::<usize>();
+
+    HEIGHT_WEIGHT * heights.iter().sum::<usize>() as f32 +
+    LINES_WEIGHT * lines as f32 +
+    HOLES_WEIGHT * holes as f32 +
+    BUMPINESS_WEIGHT * bumpiness as f32
+}
+
+pub fn best_placement(stack: &Board, tetromino: &Tetromino) -> Option<Shape> {
+    let width = stack[0].len() as i32;
+    let top = stack.len() as i32;
+
+    let mut best: Option<(f32, Shape)> = None;
+
+    for shape in orientations(tetromino) {
+        let shape_width = shape.iter().map(|position| position.0).max().unwrap() + 1;
+        let shape_height = shape.iter().map(|position| position.1).max().unwrap() + 1;
+
+        for offset_x in 0..=width - shape_width {
+            let mut placement: Shape = shape.iter().map(|&(x, y)| (x + offset_x, y + top - shape_height)).collect();
+            if !fits(stack, &placement) {
+                continue
+            }
+            loop {
+                let dropped: Shape = placement.iter().map(|&(x, y)| (x, y - 1)).collect();
+                if !fits(stack, &dropped) {
+                    break
+                }
+                placement = dropped;
+            }
+
+            let score = evaluate(stack, &placement);
+            if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+                best = Some((score, placement));
+            }
+        }
+    }
+
+    best.map(|(_, placement)| placement)
+}
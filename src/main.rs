@@ -1,37 +1,204 @@
-use std::{env::args, io::{stdout, Result}};
+use std::{env::args, io::{stdout, Result}, process::exit, str::FromStr};
 use crossterm::{
     cursor::{Hide, Show},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType, SetTitle},
+    event::{DisableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType, SetTitle},
 };
 
-use crate::{game::Game, run::run};
+use crate::{
+    daily::{record_daily, utc_day},
+    display::{Display, EnableMouseClicks},
+    game::{load_map, Game},
+    menu::main_menu,
+    mode::Mode,
+    puzzle::load_puzzles,
+    ruleset::{Ruleset, GUIDELINE},
+    run::run,
+};
 
 mod debug;
+mod chaos;
 mod config;
+mod daily;
 mod display;
 mod event;
 mod game;
+mod gravity;
+mod hint;
+mod menu;
+mod mode;
+mod particle;
+mod puzzle;
+mod rotation;
+mod ruleset;
 mod run;
 mod tetromino;
+mod theme;
+
+fn parse_arg<T: FromStr>(name: &str, value: Option<String>) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| panic!("Invalid {} argument", name))
+}
+
+// Plays games with the same settings until the player quits one, returning the last game played
+async fn play(start: impl Fn() -> Game, daily_day: Option<u64>) -> Result<Game> {
+    let mut game = start();
+    loop {
+        let restart = run(&mut game).await?;
+        if let Some(day) = daily_day.filter(|_| !game.quit) {
+            record_daily(&game, day)?;
+        }
+        if !restart {
+            return Ok(game)
+        }
+        game = start();
+    }
+}
+
+// Restores the terminal even when the game exits early with an error
+struct TerminalGuard {
+    key_releases: bool,
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = stdout();
+        if self.key_releases {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, Show, Clear(ClearType::All), DisableMouseCapture);
+        let _ = disable_raw_mode();
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Problems with the config file are reported before the terminal is taken over
+    let errors = config::validate();
+    if !errors.is_empty() {
+        eprintln!("Invalid configuration:");
+        for error in errors {
+            eprintln!("  {}", error);
+        }
+        exit(1);
+    }
+
     let mut stdout = stdout();
 
-    let args = args().collect::<Vec<String>>();
-    let level = if args.len() == 2 { args[1].parse::<u32>().unwrap() } else { 1 };
+    // The main menu is shown when no arguments are given
+    let menu = args().len() == 1;
+
+    let mut level = 1;
+    let mut mode = Mode::Endless;
+    let mut ruleset: Ruleset = GUIDELINE;
+    let mut dimension = (*config::BOARD_WIDTH, *config::BOARD_HEIGHT);
+    let mut puzzle_path: Option<String> = None;
+    let mut map_path: Option<String> = None;
+    let mut width_given = false;
+    let mut daily = false;
+    let mut name = config::PLAYER_NAME.clone();
+
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => {
+                dimension.0 = parse_arg(&arg, args.next());
+                width_given = true;
+            },
+            "--height" => dimension.1 = parse_arg(&arg, args.next()),
+            "--mode" => mode = parse_arg(&arg, args.next()),
+            "--ruleset" => ruleset = parse_arg(&arg, args.next()),
+            "--map" => map_path = Some(parse_arg(&arg, args.next())),
+            "--daily" => daily = true,
+            "--name" => name = Some(parse_arg(&arg, args.next())),
+            "--controls" => {
+                let profile: String = parse_arg(&arg, args.next());
+                if !config::controls::set_profile(&profile) {
+                    panic!("Invalid {} argument", arg);
+                }
+            },
+            "--puzzles" => {
+                puzzle_path = Some(parse_arg(&arg, args.next()));
+                mode = Mode::Puzzle;
+            },
+            _ => level = parse_arg("start_level", Some(arg)),
+        }
+    }
+
+    if dimension.0 < 4 || dimension.1 < 4 {
+        panic!("Board dimensions must be at least 4x4");
+    }
+
+    // Each co-op player spawns on their own half of the board, which needs room for an I piece
+    if mode == Mode::Coop && (width_given || map_path.is_some()) && dimension.0 < 8 {
+        panic!("Co-op boards must be at least 8 columns wide");
+    }
+
+    let puzzles = match (mode, puzzle_path) {
+        (Mode::Puzzle, Some(path)) => load_puzzles(&path, dimension)
+            .unwrap_or_else(|err| panic!("Invalid puzzle file {}: {}", path, err)),
+        (Mode::Puzzle, None) => panic!("Puzzle mode requires a --puzzles file"),
+        _ => Vec::new(),
+    };
+
+    let map = map_path.map(|path| load_map(&path, dimension)
+        .unwrap_or_else(|err| panic!("Invalid map file {}: {}", path, err)));
+
+    let daily_day = daily.then(utc_day);
 
     enable_raw_mode()?;
-    execute!(stdout, Hide, Clear(ClearType::All), SetTitle("TETRIS"))?;
+    let mut terminal = TerminalGuard { key_releases: false };
+    execute!(stdout, Hide, Clear(ClearType::All), SetTitle("TETRIS"), EnableMouseClicks)?;
+
+    // Terminals supporting the kitty keyboard protocol report key releases, which lets held keys be
+    // tracked directly instead of through the terminal's key repeat
+    let key_releases = supports_keyboard_enhancement().unwrap_or(false);
+    if key_releases {
+        execute!(stdout, PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+        ))?;
+        terminal.key_releases = true;
+    }
+
+    let start = |mode: Mode, level: u32| {
+        // Every daily challenge game on the same UTC day uses the same seed, other games get a fresh one
+        let seed = daily_day.unwrap_or_else(rand::random);
+        // Co-op gets a board twice as wide unless its width was chosen
+        let dimension = if mode == Mode::Coop && !width_given && map.is_none() {
+            (dimension.0 * 2, dimension.1)
+        } else {
+            dimension
+        };
+        let mut game = Game::start(level, dimension, mode, ruleset, seed);
+        game.player_name = name.clone();
+        game.key_releases = key_releases;
+        if mode == Mode::Puzzle {
+            game.set_puzzles(puzzles.clone());
+        } else if let Some(map) = &map {
+            game.set_board(map);
+        }
+        game
+    };
 
-    let game = &mut Game::start(level);
-    run(game).await?;
+    let mut last_game = None;
+    if menu {
+        let display = &mut Display::new(dimension)?;
+        while let Some((menu_mode, menu_level)) = main_menu(display, mode, level).await? {
+            mode = menu_mode;
+            level = menu_level;
+            last_game = Some(play(|| start(mode, level), daily_day).await?);
+        }
+    } else {
+        last_game = Some(play(|| start(mode, level), daily_day).await?);
+    }
 
-    execute!(stdout, Show, Clear(ClearType::All))?;
-    disable_raw_mode()?;
+    drop(terminal);
 
-    println!("SCORE: {}\nLEVEL: {}\nLINES: {}", game.score, game.level, game.lines);
+    if let Some(game) = last_game {
+        println!("SCORE: {}\nLEVEL: {}\nLINES: {}", game.score, game.level, game.lines);
+    }
 
     Ok(())
 }
@@ -0,0 +1,296 @@
+use std::{io::Result, time::Duration};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use futures::StreamExt;
+use strum::IntoEnumIterator;
+
+use crate::{config::{self, controls::Key}, display::{Display, LineClearAnimation}, mode::Mode};
+
+const MAX_START_LEVEL: u32 = 30;
+
+// Steps and upper limits of DAS and ARR in the settings menu
+const DAS_STEP: Duration = Duration::from_millis(10);
+const MAX_DAS: Duration = Duration::from_millis(500);
+const ARR_STEP: Duration = Duration::from_millis(5);
+const MAX_ARR: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PauseItem { Resume, Restart, Settings, Quit }
+
+pub static PAUSE_ITEMS: [PauseItem; 4] = [PauseItem::Resume, PauseItem::Restart, PauseItem::Settings, PauseItem::Quit];
+
+impl PauseItem {
+    pub fn name(&self) -> &str {
+        match self {
+            PauseItem::Resume => "RESUME",
+            PauseItem::Restart => "RESTART",
+            PauseItem::Settings => "SETTINGS",
+            PauseItem::Quit => "QUIT",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SettingItem { Ghost, FrameRate, Stats, Colorblind, Hint, LineClear, Colors, Das, Arr, Controls, Back }
+
+pub static SETTING_ITEMS: [SettingItem; 11] = [
+    SettingItem::Ghost,
+    SettingItem::FrameRate,
+    SettingItem::Stats,
+    SettingItem::Colorblind,
+    SettingItem::Hint,
+    SettingItem::LineClear,
+    SettingItem::Colors,
+    SettingItem::Das,
+    SettingItem::Arr,
+    SettingItem::Controls,
+    SettingItem::Back,
+];
+
+impl SettingItem {
+    // Kept short enough to fit inside the board, where the pause menu shows them
+    pub fn label(&self) -> String {
+        let settings = config::settings();
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            SettingItem::Ghost => format!("GHOST: {}", on_off(settings.show_ghost)),
+            SettingItem::FrameRate => format!("FPS: {}", on_off(settings.display_frame_rate)),
+            SettingItem::Stats => format!("STATS: {}", on_off(settings.show_stats)),
+            SettingItem::Colorblind => format!("COLORBLIND: {}", on_off(settings.colorblind)),
+            SettingItem::Hint => format!("HINT: {}", on_off(settings.show_hint)),
+            SettingItem::LineClear => format!("CLEAR: {}", settings.line_clear_animation.to_string().to_uppercase()),
+            SettingItem::Colors => format!("COLORS: {}", if settings.use_xterm_256_colors { 256 } else { 16 }),
+            SettingItem::Das => format!("DAS: {}MS", settings.das.as_millis()),
+            SettingItem::Arr => format!("ARR: {}MS", settings.arr.as_millis()),
+            SettingItem::Controls => format!("KEYS: {}", config::controls::profile().to_uppercase()),
+            SettingItem::Back => String::from("BACK"),
+        }
+    }
+
+    // Steps the setting to its next or previous value and saves it to the config file, returning
+    // the error if it couldn't be saved
+    pub fn change(&self, forward: bool) -> Result<()> {
+        let mut settings = config::settings_mut();
+        match self {
+            SettingItem::Ghost => settings.show_ghost = !settings.show_ghost,
+            SettingItem::FrameRate => settings.display_frame_rate = !settings.display_frame_rate,
+            SettingItem::Stats => settings.show_stats = !settings.show_stats,
+            SettingItem::Colorblind => settings.colorblind = !settings.colorblind,
+            SettingItem::Hint => settings.show_hint = !settings.show_hint,
+            SettingItem::LineClear => {
+                let animations: Vec<LineClearAnimation> = LineClearAnimation::iter().collect();
+                let index = animations.iter().position(|&item| item == settings.line_clear_animation).unwrap();
+                settings.line_clear_animation = if forward {
+                    animations[(index + 1) % animations.len()]
+                } else {
+                    animations[(index + animations.len() - 1) % animations.len()]
+                };
+            },
+            SettingItem::Colors => settings.use_xterm_256_colors = !settings.use_xterm_256_colors,
+            SettingItem::Das => settings.das = step(settings.das, DAS_STEP, MAX_DAS, forward),
+            SettingItem::Arr => settings.arr = step(settings.arr, ARR_STEP, MAX_ARR, forward),
+            SettingItem::Controls => config::controls::cycle_profile(forward),
+            SettingItem::Back => return Ok(()),
+        }
+        drop(settings);
+        if *self == SettingItem::Colors {
+            config::reload_theme();
+        }
+        config::save_settings()
+    }
+}
+
+fn step(value: Duration, step: Duration, max: Duration, forward: bool) -> Duration {
+    if forward { (value + step).min(max) } else { value.saturating_sub(step) }
+}
+
+// Shown in place of a menu's title when the settings couldn't be saved
+fn save_error_notice(err: std::io::Error) -> String {
+    format!("COULDN'T SAVE: {}", err).to_uppercase()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuItem { Start, Mode, Level, Settings, Controls, Quit }
+
+static MENU_ITEMS: [MenuItem; 6] = [
+    MenuItem::Start,
+    MenuItem::Mode,
+    MenuItem::Level,
+    MenuItem::Settings,
+    MenuItem::Controls,
+    MenuItem::Quit,
+];
+
+// Mouse input reaches menus as the keys it stands for
+fn menu_key(event: Event, display: &Display, selected: &mut usize) -> Option<KeyCode> {
+    match event {
+        Event::Key(KeyEvent { kind: KeyEventKind::Press, code, .. }) => Some(code),
+        Event::Mouse(MouseEvent { kind: MouseEventKind::ScrollUp, .. }) => Some(KeyCode::Up),
+        Event::Mouse(MouseEvent { kind: MouseEventKind::ScrollDown, .. }) => Some(KeyCode::Down),
+        Event::Mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column, row, .. }) => {
+            *selected = display.menu_item_at(column, row)?;
+            Some(KeyCode::Enter)
+        },
+        _ => None,
+    }
+}
+
+// Shows the settings screen until the player goes back
+async fn settings_menu(display: &mut Display, reader: &mut EventStream) -> Result<()> {
+    let mut selected = 0;
+    let mut notice: Option<String> = None;
+
+    loop {
+        let items: Vec<String> = SETTING_ITEMS.iter().map(SettingItem::label).collect();
+
+        display.render_menu(notice.as_deref().unwrap_or("SETTINGS"), &items, selected)?;
+
+        let Some(Ok(event)) = reader.next().await else {
+            return Ok(())
+        };
+
+        if let Some(code) = menu_key(event, display, &mut selected) {
+            let saved = match code {
+                KeyCode::Up => {
+                    selected = (selected + SETTING_ITEMS.len() - 1) % SETTING_ITEMS.len();
+                    Ok(())
+                },
+                KeyCode::Down => {
+                    selected = (selected + 1) % SETTING_ITEMS.len();
+                    Ok(())
+                },
+                KeyCode::Left | KeyCode::Right => SETTING_ITEMS[selected].change(code == KeyCode::Right),
+                KeyCode::Enter | KeyCode::Char(' ') => match SETTING_ITEMS[selected] {
+                    SettingItem::Back => return Ok(()),
+                    item => item.change(true),
+                },
+                KeyCode::Esc => return Ok(()),
+                _ => Ok(()),
+            };
+            notice = saved.err().map(save_error_notice);
+        }
+    }
+}
+
+// Keys already bound to another action are turned down
+async fn controls_menu(display: &mut Display, reader: &mut EventStream) -> Result<()> {
+    let mut selected = 0;
+    let mut binding = false;
+    let mut notice: Option<String> = None;
+
+    loop {
+        let bindings = config::controls::bindings();
+        let mut items: Vec<String> = bindings.iter().enumerate().map(|(i, (action, keys))| {
+            if binding && i == selected {
+                format!("{}: <PRESS A KEY>", action.name())
+            } else {
+                format!("{}: {}", action.name(), keys.to_uppercase())
+            }
+        }).collect();
+        items.push(String::from("BACK"));
+
+        let title = format!("CONTROLS: {}", config::controls::profile().to_uppercase());
+        display.render_menu(notice.as_deref().unwrap_or(&title), &items, selected)?;
+
+        let Some(Ok(event)) = reader.next().await else {
+            return Ok(())
+        };
+
+        if binding {
+            let Event::Key(key @ KeyEvent { kind: KeyEventKind::Press, code, .. }) = event else {
+                continue
+            };
+            binding = false;
+            notice = None;
+            let action = &bindings[selected].0;
+            let key = Key::from(&key);
+            match (config::controls::key_name(key), config::controls::bound_action(key)) {
+                _ if code == KeyCode::Esc => (),
+                (None, _) => notice = Some(String::from("KEY CAN'T BE BOUND")),
+                (Some(_), Some(bound)) if bound != *action => notice = Some(format!("KEY USED BY {}", bound.name())),
+                _ => {
+                    config::controls::rebind(action, key);
+                    notice = config::save_settings().err().map(save_error_notice);
+                },
+            }
+            continue
+        }
+
+        let Some(code) = menu_key(event, display, &mut selected) else {
+            continue
+        };
+
+        notice = None;
+
+        match code {
+            KeyCode::Up => selected = (selected + items.len() - 1) % items.len(),
+            KeyCode::Down => selected = (selected + 1) % items.len(),
+            KeyCode::Enter | KeyCode::Char(' ') if selected == bindings.len() => return Ok(()),
+            KeyCode::Enter | KeyCode::Char(' ') => binding = true,
+            KeyCode::Esc => return Ok(()),
+            _ => (),
+        }
+    }
+}
+
+// Returns None if the player quits
+pub async fn main_menu(display: &mut Display, mut mode: Mode, mut level: u32) -> Result<Option<(Mode, u32)>> {
+    let mut reader = EventStream::new();
+
+    // Puzzle mode needs a puzzle file, so it can only be started with --puzzles
+    let modes: Vec<Mode> = Mode::iter().filter(|mode| *mode != Mode::Puzzle).collect();
+    let max_level = config::MAX_LEVEL.unwrap_or(MAX_START_LEVEL);
+
+    let mut selected = 0;
+
+    loop {
+        let items: Vec<String> = MENU_ITEMS.iter().map(|item| match item {
+            MenuItem::Start => String::from("START"),
+            MenuItem::Mode => format!("MODE:  < {} >", mode.name()),
+            MenuItem::Level => format!("LEVEL: < {} >", level),
+            MenuItem::Settings => String::from("SETTINGS"),
+            MenuItem::Controls => String::from("CONTROLS"),
+            MenuItem::Quit => String::from("QUIT"),
+        }).collect();
+
+        display.render_menu("TETRIS", &items, selected)?;
+
+        let Some(Ok(event)) = reader.next().await else {
+            return Ok(None)
+        };
+
+        if let Some(code) = menu_key(event, display, &mut selected) {
+            match code {
+                KeyCode::Up => selected = (selected + MENU_ITEMS.len() - 1) % MENU_ITEMS.len(),
+                KeyCode::Down => selected = (selected + 1) % MENU_ITEMS.len(),
+                // Picking the mode or level steps it forward, which is how clicking them works
+                KeyCode::Left | KeyCode::Right | KeyCode::Enter | KeyCode::Char(' ')
+                    if matches!(MENU_ITEMS[selected], MenuItem::Mode | MenuItem::Level) => {
+                    let forward = code != KeyCode::Left;
+                    match MENU_ITEMS[selected] {
+                        MenuItem::Mode => {
+                            let index = modes.iter().position(|&item| item == mode).unwrap_or(0);
+                            mode = if forward {
+                                modes[(index + 1) % modes.len()]
+                            } else {
+                                modes[(index + modes.len() - 1) % modes.len()]
+                            };
+                        },
+                        MenuItem::Level => {
+                            level = if forward { (level + 1).min(max_level) } else { (level - 1).max(1) };
+                        },
+                        _ => (),
+                    }
+                },
+                KeyCode::Enter | KeyCode::Char(' ') => match MENU_ITEMS[selected] {
+                    MenuItem::Start => return Ok(Some((mode, level))),
+                    MenuItem::Settings => settings_menu(display, &mut reader).await?,
+                    MenuItem::Controls => controls_menu(display, &mut reader).await?,
+                    MenuItem::Quit => return Ok(None),
+                    _ => (),
+                },
+                KeyCode::Esc => return Ok(None),
+                _ => (),
+            }
+        }
+    }
+}
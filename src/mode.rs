@@ -0,0 +1,91 @@
+use core::time::Duration;
+use strum_macros::{EnumIter, EnumString};
+
+use crate::config;
+
+const SPRINT_LINES: u32 = 40;
+const ULTRA_DURATION: Duration = Duration::from_secs(120);
+
+// Survival garbage starts every 4 seconds and comes 10% faster every 30 seconds, down to twice a second
+const SURVIVAL_START_INTERVAL: Duration = Duration::from_secs(4);
+const SURVIVAL_MIN_INTERVAL: Duration = Duration::from_millis(500);
+const SURVIVAL_SPEED_UP_INTERVAL: Duration = Duration::from_secs(30);
+const SURVIVAL_SPEED_UP: f32 = 0.9;
+
+#[derive(Clone, Copy, EnumIter, EnumString, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Mode {
+    Endless,
+    Marathon,
+    Sprint,
+    Ultra,
+    Cheese,
+    Zen,
+    Puzzle,
+    Garbage,
+    Survival,
+    TimeTrial,
+    Combo,
+    Chaos,
+    Coop,
+}
+
+impl Mode {
+    pub fn name(&self) -> &str {
+        match self {
+            Mode::Endless => "ENDLESS",
+            Mode::Marathon => "MARATHON",
+            Mode::Sprint => "SPRINT",
+            Mode::Ultra => "ULTRA",
+            Mode::Cheese => "CHEESE RACE",
+            Mode::Zen => "ZEN",
+            Mode::Puzzle => "PUZZLE",
+            Mode::Garbage => "GARBAGE TRAINING",
+            Mode::Survival => "SURVIVAL",
+            Mode::TimeTrial => "TIME TRIAL",
+            Mode::Combo => "4-WIDE COMBO",
+            Mode::Chaos => "CHAOS",
+            Mode::Coop => "CO-OP",
+        }
+    }
+
+    pub fn line_goal(&self) -> Option<u32> {
+        match self {
+            Mode::Marathon => Some(*config::MARATHON_LINES),
+            Mode::Sprint => Some(SPRINT_LINES),
+            _ => None,
+        }
+    }
+
+    pub fn target_level(&self) -> Option<u32> {
+        match self {
+            Mode::TimeTrial => Some(*config::TRIAL_LEVEL),
+            _ => None,
+        }
+    }
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        match self {
+            Mode::Ultra => Some(ULTRA_DURATION),
+            _ => None,
+        }
+    }
+
+    pub fn garbage_interval(&self, time_played: Duration) -> Option<Duration> {
+        match self {
+            Mode::Garbage => Some(*config::GARBAGE_INTERVAL),
+            Mode::Survival => {
+                let speed_ups = (time_played.as_secs_f32() / SURVIVAL_SPEED_UP_INTERVAL.as_secs_f32()).floor();
+                Some(SURVIVAL_START_INTERVAL.mul_f32(SURVIVAL_SPEED_UP.powf(speed_ups)).max(SURVIVAL_MIN_INTERVAL))
+            },
+            _ => None,
+        }
+    }
+
+    pub fn complete_title(&self) -> String {
+        match self {
+            Mode::Ultra => String::from("TIME UP"),
+            _ => format!("{} COMPLETE", self.name()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use core::time::Duration;
+use crossterm::style::Color;
+use rand::{seq::SliceRandom, Rng};
+use tokio::time::Instant;
+
+const LIFETIME: Duration = Duration::from_millis(500);
+
+// Rows per second squared pulling particles back down
+const GRAVITY: f32 = 40.0;
+
+const GLYPHS: [&str; 4] = ["*", "+", "'", "."];
+
+// Positioned in terminal columns and rows
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    velocity: (f32, f32),
+    pub glyph: &'static str,
+    pub color: Color,
+    born: Instant,
+}
+
+impl Particle {
+    pub fn new(x: f32, y: f32, color: Color, rng: &mut impl Rng) -> Self {
+        Particle {
+            x,
+            y,
+            velocity: (rng.gen_range(-20.0..20.0), rng.gen_range(-15.0..-5.0)),
+            glyph: GLYPHS.choose(rng).unwrap(),
+            color,
+            born: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f32();
+        self.x += self.velocity.0 * seconds;
+        self.y += self.velocity.1 * seconds;
+        self.velocity.1 += GRAVITY * seconds;
+    }
+
+    pub fn expired(&self) -> bool {
+        self.born.elapsed() >= LIFETIME
+    }
+}
@@ -0,0 +1,163 @@
+use std::{fs::read_to_string, str::FromStr};
+
+use crate::{display::Dimension, game::{parse_board_row, Board, Spin}, tetromino::TetrominoVariant};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Objective {
+    Lines(u32),
+    TSpin(u32),
+    Tetris,
+    PerfectClear,
+}
+
+impl FromStr for Objective {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        match s.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["lines", lines] => lines.parse().map(Objective::Lines).map_err(|_| ()),
+            ["tspin", "single"] => Ok(Objective::TSpin(1)),
+            ["tspin", "double"] => Ok(Objective::TSpin(2)),
+            ["tspin", "triple"] => Ok(Objective::TSpin(3)),
+            ["tetris"] => Ok(Objective::Tetris),
+            ["perfect", "clear"] => Ok(Objective::PerfectClear),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Objective {
+    pub fn name(&self) -> String {
+        match self {
+            Objective::Lines(1) => String::from("CLEAR 1 LINE"),
+            Objective::Lines(lines) => format!("CLEAR {} LINES", lines),
+            Objective::TSpin(1) => String::from("T-SPIN SINGLE"),
+            Objective::TSpin(2) => String::from("T-SPIN DOUBLE"),
+            Objective::TSpin(_) => String::from("T-SPIN TRIPLE"),
+            Objective::Tetris => String::from("TETRIS"),
+            Objective::PerfectClear => String::from("PERFECT CLEAR"),
+        }
+    }
+
+    // `puzzle_lines` includes the rows cleared by this placement
+    pub fn met(&self, num_cleared: u32, puzzle_lines: u32, spin: Spin, perfect_clear: bool) -> bool {
+        match *self {
+            Objective::Lines(lines) => puzzle_lines >= lines,
+            Objective::TSpin(lines) => spin == Spin::Full && num_cleared == lines,
+            Objective::Tetris => num_cleared == 4,
+            Objective::PerfectClear => num_cleared > 0 && perfect_clear,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub board: Board,
+    pub queue: Vec<TetrominoVariant>,
+    pub objective: Objective,
+}
+
+fn parse_puzzle(name: &str, lines: &[(usize, &str)], dimension: Dimension) -> Result<Puzzle, String> {
+    let mut board = Vec::new();
+    let mut queue = Vec::new();
+    let mut objective = None;
+
+    for &(number, line) in lines {
+        let error = |message: &str| format!("line {}: {}", number, message);
+        match line.split_once('=') {
+            Some((key, value)) => match key.trim() {
+                "queue" => queue = value
+                    .chars()
+                    .filter(|char| !char.is_whitespace())
+                    .map(|char| TetrominoVariant::from_char(char).ok_or_else(|| error("invalid piece in queue")))
+                    .collect::<Result<_, _>>()?,
+                "goal" => objective = Some(value.parse().map_err(|_| error("invalid goal"))?),
+                key => return Err(error(&format!("unknown key {}", key))),
+            },
+            None => board.push(parse_board_row(line, dimension.0).map_err(|err| error(&err))?),
+        }
+    }
+
+    if board.len() > dimension.1 as usize {
+        return Err(format!("puzzle {}: board is taller than {} rows", name, dimension.1))
+    }
+    if queue.is_empty() {
+        return Err(format!("puzzle {}: missing queue", name))
+    }
+
+    // Rows are written top to bottom, but the stack is indexed from the bottom
+    board.reverse();
+
+    Ok(Puzzle {
+        name: name.to_string(),
+        board,
+        queue,
+        objective: objective.ok_or_else(|| format!("puzzle {}: missing goal", name))?,
+    })
+}
+
+pub fn load_puzzles(path: &str, dimension: Dimension) -> Result<Vec<Puzzle>, String> {
+    let contents = read_to_string(path).map_err(|err| err.to_string())?;
+
+    let mut sections: Vec<(&str, Vec<(usize, &str)>)> = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            sections.push((name.trim(), Vec::new()));
+        } else if let Some((_, lines)) = sections.last_mut() {
+            lines.push((i + 1, line));
+        } else {
+            return Err(format!("line {}: expected a [puzzle] header", i + 1))
+        }
+    }
+
+    if sections.is_empty() {
+        return Err(String::from("no puzzles found"))
+    }
+
+    sections
+        .iter()
+        .map(|(name, lines)| parse_puzzle(name, lines, dimension))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::{remove_file, write}};
+
+    use super::*;
+
+    fn load(name: &str, contents: &str) -> Result<Vec<Puzzle>, String> {
+        let path = temp_dir().join(format!("tetris_{}_{}.txt", name, std::process::id()));
+        write(&path, contents).unwrap();
+        let puzzles = load_puzzles(path.to_str().unwrap(), (10, 20));
+        remove_file(&path).unwrap();
+        puzzles
+    }
+
+    #[test]
+    fn loads_bundled_puzzles() {
+        let puzzles = load_puzzles("puzzles.txt", (10, 20)).unwrap();
+        assert_eq!(puzzles[0].name, "Square Hole");
+        assert!(puzzles[0].objective == Objective::PerfectClear);
+        assert!(puzzles[0].queue == vec![TetrominoVariant::O]);
+        assert_eq!(puzzles[0].board.len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_puzzles() {
+        assert_eq!(load("empty", "; nothing\n").err(), Some(String::from("no puzzles found")));
+        assert_eq!(load("header", "queue = T\n").err(), Some(String::from("line 1: expected a [puzzle] header")));
+        assert_eq!(load("piece", "[A]\nqueue = TQ\n").err(), Some(String::from("line 2: invalid piece in queue")));
+        assert_eq!(load("goal", "[A]\nqueue = T\ngoal = win\n").err(), Some(String::from("line 3: invalid goal")));
+        assert_eq!(load("key", "[A]\nnext = T\n").err(), Some(String::from("line 2: unknown key next")));
+        assert_eq!(load("queue", "[A]\ngoal = tetris\n").err(), Some(String::from("puzzle A: missing queue")));
+        assert_eq!(load("missing_goal", "[A]\nqueue = I\n").err(), Some(String::from("puzzle A: missing goal")));
+    }
+}
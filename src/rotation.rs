@@ -0,0 +1,136 @@
+use crate::{display::Dimension, tetromino::{CardinalDirection, TetrominoVariant}};
+
+static JLSTZ_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)], // North
+    [( 0,  0), ( 1,  0), ( 1, -1), ( 0,  2), ( 1,  2)], // East
+    [( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)], // South
+    [( 0,  0), (-1,  0), (-1, -1), ( 0,  2), (-1,  2)], // West
+];
+
+static I_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [( 0,  0), (-1,  0), ( 2,  0), (-1,  0), ( 2,  0)],
+    [(-1,  0), ( 0,  0), ( 0,  0), ( 0,  1), ( 0, -2)],
+    [(-1,  1), ( 1,  1), (-2,  1), ( 1,  0), (-2,  0)],
+    [( 0,  1), ( 0,  1), ( 0,  1), ( 0, -1), ( 0,  2)],
+];
+
+static O_OFFSETS: [[(i32, i32); 5]; 4] = [
+    [( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
+    [( 0, -1), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
+    [(-1, -1), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
+    [(-1,  0), ( 0,  0), ( 0,  0), ( 0,  0), ( 0,  0)],
+];
+
+static HALF_TURN_KICKS: [[(i32, i32); 6]; 4] = [
+    [( 0,  0), ( 0,  1), ( 1,  1), (-1,  1), ( 1,  0), (-1,  0)], // North -> South
+    [( 0,  0), ( 1,  0), ( 1,  2), ( 1,  1), ( 0,  2), ( 0,  1)], // East -> West
+    [( 0,  0), ( 0, -1), (-1, -1), ( 1, -1), (-1,  0), ( 1,  0)], // South -> North
+    [( 0,  0), (-1,  0), (-1,  2), (-1,  1), ( 0,  2), ( 0,  1)], // West -> East
+];
+
+// Tries one cell right and then one cell left
+static SIMPLE_KICKS: [(i32, i32); 3] = [(0, 0), (1, 0), (-1, 0)];
+
+fn offset_table(variant: TetrominoVariant) -> &'static [[(i32, i32); 5]; 4] {
+    match variant {
+        TetrominoVariant::J |
+        TetrominoVariant::L |
+        TetrominoVariant::S |
+        TetrominoVariant::T |
+        TetrominoVariant::Z => &JLSTZ_OFFSETS,
+        TetrominoVariant::I => &I_OFFSETS,
+        TetrominoVariant::O => &O_OFFSETS,
+    }
+}
+
+// Translation that keeps a piece rotated about its center aligned to the grid, without any kick
+fn base_kick(variant: TetrominoVariant, from: CardinalDirection, to: CardinalDirection) -> Dimension {
+    let offset_table = offset_table(variant);
+    (
+        offset_table[from as usize][0].0 - offset_table[to as usize][0].0,
+        offset_table[from as usize][0].1 - offset_table[to as usize][0].1,
+    )
+}
+
+fn is_half_turn(from: CardinalDirection, to: CardinalDirection) -> bool {
+    (from as i32 - to as i32).abs() == 2
+}
+
+// Table a rotation took its kicks from, as the index of a kick only means something within its table
+#[derive(Clone, Copy, PartialEq)]
+pub enum KickTable {
+    SrsQuarterTurn,
+    SrsHalfTurn,
+    Simple,
+    Classic,
+}
+
+pub trait RotationSystem: Send + Sync {
+    // The first translation that doesn't collide is used
+    fn kicks(&self, variant: TetrominoVariant, from: CardinalDirection, to: CardinalDirection) -> Vec<Dimension>;
+
+    fn kick_table(&self, from: CardinalDirection, to: CardinalDirection) -> KickTable;
+}
+
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn kicks(&self, variant: TetrominoVariant, from: CardinalDirection, to: CardinalDirection) -> Vec<Dimension> {
+        if is_half_turn(from, to) {
+            let base = base_kick(variant, from, to);
+            return HALF_TURN_KICKS[from as usize]
+                .iter()
+                .map(|&(x, y)| (base.0 + x, base.1 + y))
+                .collect()
+        }
+
+        let offset_table = offset_table(variant);
+        offset_table[from as usize]
+            .iter()
+            .zip(offset_table[to as usize].iter())
+            .map(|(from_offset, to_offset)| (from_offset.0 - to_offset.0, from_offset.1 - to_offset.1))
+            .collect()
+    }
+
+    fn kick_table(&self, from: CardinalDirection, to: CardinalDirection) -> KickTable {
+        if is_half_turn(from, to) { KickTable::SrsHalfTurn } else { KickTable::SrsQuarterTurn }
+    }
+}
+
+// SRS rotation states with only sideways kicks, where the I piece never kicks
+pub struct Simple;
+
+impl RotationSystem for Simple {
+    fn kicks(&self, variant: TetrominoVariant, from: CardinalDirection, to: CardinalDirection) -> Vec<Dimension> {
+        let base = base_kick(variant, from, to);
+        if variant == TetrominoVariant::I {
+            return vec![base]
+        }
+        SIMPLE_KICKS.iter().map(|&(x, y)| (base.0 + x, base.1 + y)).collect()
+    }
+
+    fn kick_table(&self, _from: CardinalDirection, _to: CardinalDirection) -> KickTable {
+        KickTable::Simple
+    }
+}
+
+pub struct Classic;
+
+impl RotationSystem for Classic {
+    fn kicks(&self, variant: TetrominoVariant, from: CardinalDirection, to: CardinalDirection) -> Vec<Dimension> {
+        vec![base_kick(variant, from, to)]
+    }
+
+    fn kick_table(&self, _from: CardinalDirection, _to: CardinalDirection) -> KickTable {
+        KickTable::Classic
+    }
+}
+
+pub fn rotation_system(name: &str) -> Option<Box<dyn RotationSystem>> {
+    match name.trim().to_lowercase().as_str() {
+        "srs"       => Some(Box::new(Srs)),
+        "simple"    => Some(Box::new(Simple)),
+        "classic"   => Some(Box::new(Classic)),
+        _           => None,
+    }
+}
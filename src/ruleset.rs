@@ -0,0 +1,58 @@
+use std::str::FromStr;
+
+use crate::gravity::SpeedCurve;
+
+// How the upcoming pieces are picked
+#[derive(Clone, Copy, PartialEq)]
+pub enum Randomizer {
+    // Every piece once in a random order, then again
+    SevenBag,
+    // Each piece picked at random, with one reroll when it repeats the previous piece
+    Nes,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Ruleset {
+    // Points per level for clearing one to four lines
+    pub line_clear_points: [u32; 4],
+    // Spins, combos and perfect clears
+    pub bonuses: bool,
+    pub hold: bool,
+    pub ghost: bool,
+    pub previews: usize,
+    // Replaces the speed_curve option
+    pub speed_curve: Option<SpeedCurve>,
+    pub randomizer: Randomizer,
+}
+
+pub const GUIDELINE: Ruleset = Ruleset {
+    line_clear_points: [100, 300, 500, 800],
+    bonuses: true,
+    hold: true,
+    ghost: true,
+    previews: 3,
+    speed_curve: None,
+    randomizer: Randomizer::SevenBag,
+};
+
+pub const NES: Ruleset = Ruleset {
+    line_clear_points: [40, 100, 300, 1200],
+    bonuses: false,
+    hold: false,
+    ghost: false,
+    previews: 1,
+    speed_curve: Some(SpeedCurve::Nes),
+    randomizer: Randomizer::Nes,
+};
+
+impl FromStr for Ruleset {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "guideline" => Ok(GUIDELINE),
+            "nes"       => Ok(NES),
+            _           => Err(()),
+        }
+    }
+}
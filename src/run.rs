@@ -1,14 +1,13 @@
 use std::io::Result;
 use crossterm::event::EventStream;
 use futures::{stream::StreamExt, FutureExt};
-use tokio::{pin, select, time::{interval, sleep, Duration, Interval}};
+use tokio::{pin, select, time::{interval, sleep, sleep_until, Duration, Instant, Interval}};
 
-use crate::{config, display::Display, event::handle_event, game::{Game, ShiftDirection}};
+use crate::{chaos, config, display::Display, event::{handle_event, handle_results_event}, game::Game, mode::Mode};
 
-fn calc_drop_interval(level: u32) -> Interval {
-    let drop_rate = (0.8 - (level - 1) as f32 * 0.007).powf((level - 1) as f32);
-    let drop_duration = Duration::from_nanos((drop_rate * 1_000_000_000f32) as u64);
+const SPAWN_RETRY_INTERVAL: Duration = Duration::from_millis(50);
 
+fn calc_drop_interval(drop_duration: Duration) -> Interval {
     interval(if drop_duration.is_zero() {
         Duration::from_nanos(1)
     } else {
@@ -16,67 +15,179 @@ fn calc_drop_interval(level: u32) -> Interval {
     })
 }
 
-pub async fn run(game: &mut Game) -> Result<()> {
+// Plays a game until it ends, returning whether the player chose to restart from the results screen
+pub async fn run(game: &mut Game) -> Result<bool> {
     let mut reader = EventStream::new();
 
-    let display = &mut Display::new()?;
+    let display = &mut Display::new(game.dimension)?;
+    if let Some(name) = &game.player_name {
+        display.title = name.clone();
+    }
     display.draw()?;
 
-    let frame_duration = Duration::from_nanos(if *config::MAX_FRAME_RATE > 0 {
-        1_000_000_000 / *config::MAX_FRAME_RATE
-    } else {
-        1
-    });
+    let frame_duration = Duration::from_nanos(
+        1_000_000_000u64.checked_div(*config::MAX_FRAME_RATE).unwrap_or(1)
+    );
 
     let mut render_interval = interval(frame_duration);
-    let mut drop_interval = calc_drop_interval(game.level);
+    let mut drop_interval = calc_drop_interval(game.drop_duration());
+    // The co-op partner falls on its own timer so that soft dropping only speeds up one piece
+    let partner_drop_duration = |game: &mut Game| game.with_partner(|game| game.drop_duration());
+    let mut partner_drop_interval = calc_drop_interval(partner_drop_duration(game).unwrap_or_default());
 
-    let mut prev_level = game.level;
+    let mut prev_drop_duration = game.drop_duration();
+    let mut prev_partner_drop_duration = partner_drop_duration(game);
+    let mut prev_paused = game.paused;
 
     pin! {
         let lock_delay = sleep(Duration::ZERO);
+        let partner_lock_delay = sleep(Duration::ZERO);
         let line_clear_delay = sleep(Duration::ZERO);
+        let garbage_delay = sleep(game.garbage_interval().unwrap_or_default());
+        let chaos_delay = sleep(chaos::event_interval(&mut game.rng));
     }
 
+    game.garbage_due = garbage_delay.deadline();
+
+    let mut lock_remaining = Duration::ZERO;
+    let mut partner_lock_remaining = Duration::ZERO;
+    let mut line_clear_remaining = Duration::ZERO;
+    let mut garbage_remaining = Duration::ZERO;
+    let mut chaos_remaining = Duration::ZERO;
+
     let mut debug_frame_interval = interval(Duration::from_secs(1));
     let mut debug_frame = 0u64;
 
-    Ok(loop {
+    loop {
+        // Pausing and resuming are handled before anything else so that timers which ran out during
+        // the pause are restored before they can fire
         select! {
+            biased;
+
+            _ = async {}, if game.paused != prev_paused => {
+                prev_paused = game.paused;
+                let now = Instant::now();
+                if game.paused {
+                    lock_remaining = lock_delay.deadline().saturating_duration_since(now);
+                    partner_lock_remaining = partner_lock_delay.deadline().saturating_duration_since(now);
+                    line_clear_remaining = line_clear_delay.deadline().saturating_duration_since(now);
+                    garbage_remaining = garbage_delay.deadline().saturating_duration_since(now);
+                    chaos_remaining = chaos_delay.deadline().saturating_duration_since(now);
+                } else {
+                    lock_delay.as_mut().reset(now + lock_remaining);
+                    partner_lock_delay.as_mut().reset(now + partner_lock_remaining);
+                    line_clear_delay.as_mut().reset(now + line_clear_remaining);
+                    garbage_delay.as_mut().reset(now + garbage_remaining);
+                    game.garbage_due = garbage_delay.deadline();
+                    chaos_delay.as_mut().reset(now + chaos_remaining);
+                    drop_interval.reset();
+                    partner_drop_interval.reset();
+                }
+            },
             Some(Ok(event)) = reader.next().fuse() => {
                 handle_event(
                     game,
                     event,
                     display,
                     &mut lock_delay,
+                    &mut partner_lock_delay,
                     &mut line_clear_delay,
                 )?
             },
-            _ = &mut lock_delay, if game.locking => {
+            _ = &mut lock_delay, if game.player.locking && !game.player.spawning && !game.paused => {
                 game.place(&mut line_clear_delay);
             },
-            _ = &mut line_clear_delay, if game.clearing.len() > 0 => {
-                game.line_clear();
+            _ = &mut partner_lock_delay, if game.partner_is(|game| game.player.locking && !game.player.spawning) && !game.paused => {
+                game.with_partner(|game| game.place(&mut line_clear_delay));
+            },
+            _ = &mut line_clear_delay, if (!game.clearing.is_empty() || game.player.spawning || game.partner_is(|game| game.player.spawning)) && !game.paused => {
+                if !game.clearing.is_empty() {
+                    game.line_clear();
+                }
+                if game.player.spawning {
+                    game.spawn();
+                }
+                if game.partner_is(|game| game.player.spawning) && !game.end {
+                    game.with_partner(Game::spawn);
+                }
+                // A spawn held back by the other co-op piece is tried again shortly
+                if game.player.spawning || game.partner_is(|game| game.player.spawning) {
+                    line_clear_delay.as_mut().reset(Instant::now() + SPAWN_RETRY_INTERVAL);
+                }
+            },
+            _ = drop_interval.tick(), if !game.twenty_g() && !game.player.spawning && !game.paused => {
+                game.fall(&mut lock_delay, &mut line_clear_delay);
+            },
+            _ = partner_drop_interval.tick(), if game.partner_is(|game| !game.twenty_g() && !game.player.spawning) && !game.paused => {
+                game.with_partner(|game| game.fall(&mut partner_lock_delay, &mut line_clear_delay));
+            },
+            _ = &mut garbage_delay, if game.garbage_interval().is_some() && !game.paused => {
+                game.rise_garbage();
+                garbage_delay.as_mut().reset(Instant::now() + game.garbage_interval().unwrap());
+                game.garbage_due = garbage_delay.deadline();
+            },
+            _ = &mut chaos_delay, if game.mode == Mode::Chaos && !game.paused => {
+                let duration = game.chaos_tick();
+                chaos_delay.as_mut().reset(Instant::now() + duration);
+            },
+            _ = sleep_until(game.player.shift_repeat_at), if game.player.held_shift.is_some() && !game.player.spawning && !game.paused => {
+                game.auto_shift(&mut lock_delay, &mut line_clear_delay);
+            },
+            _ = sleep_until(game.player.soft_drop_until), if game.player.soft_dropping && !game.key_releases => {
+                game.player.soft_dropping = false;
+            },
+            _ = sleep_until(game.partner.as_ref().map_or(Instant::now(), |partner| partner.shift_repeat_at)),
+                if game.partner_is(|game| game.player.held_shift.is_some() && !game.player.spawning) && !game.paused => {
+                game.with_partner(|game| game.auto_shift(&mut partner_lock_delay, &mut line_clear_delay));
             },
-            _ = drop_interval.tick() => {
-                game.shift(ShiftDirection::Down, &mut lock_delay, &mut line_clear_delay);
+            _ = sleep_until(game.partner.as_ref().map_or(Instant::now(), |partner| partner.soft_drop_until)),
+                if game.partner_is(|game| game.player.soft_dropping) && !game.key_releases => {
+                game.with_partner(|game| game.player.soft_dropping = false);
+            },
+            _ = async {}, if game.gravity_pending() => {
+                game.gravity_drop(&mut lock_delay);
+            },
+            _ = async {}, if game.partner_is(|game| game.gravity_pending()) => {
+                game.with_partner(|game| game.gravity_drop(&mut partner_lock_delay));
             },
             _ = render_interval.tick() => {
                 display.render(game)?;
-                debug_frame += *config::DISPLAY_FRAME_RATE as u64;
+                debug_frame += config::settings().display_frame_rate as u64;
             },
-            _ = debug_frame_interval.tick(), if *config::DISPLAY_FRAME_RATE => {
+            _ = debug_frame_interval.tick(), if config::settings().display_frame_rate => {
                 display.render_debug_info(debug_frame)?;
                 debug_frame = 0;
             },
-            _ = async {}, if game.level != prev_level => {
-                prev_level = game.level;
-                drop_interval = calc_drop_interval(game.level);
+            _ = async {}, if game.drop_duration() != prev_drop_duration => {
+                prev_drop_duration = game.drop_duration();
+                drop_interval = calc_drop_interval(prev_drop_duration);
+            },
+            _ = async {}, if partner_drop_duration(game) != prev_partner_drop_duration => {
+                prev_partner_drop_duration = partner_drop_duration(game);
+                partner_drop_interval = calc_drop_interval(prev_partner_drop_duration.unwrap_or_default());
+            },
+            _ = async {}, if !game.end && game.goal_reached() => {
+                game.complete = true;
+                game.end = true;
             },
             _ = async {}, if game.end => {
+                game.end_time = Some(Instant::now());
                 break;
             },
         }
-    })
-}
+    }
 
+    if game.quit {
+        return Ok(game.restart)
+    }
+
+    display.render_results(game)?;
+
+    while let Some(Ok(event)) = reader.next().await {
+        if let Some(restart) = handle_results_event(game, event, display)? {
+            return Ok(restart)
+        }
+    }
+
+    Ok(false)
+}
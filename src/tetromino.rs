@@ -12,6 +12,21 @@ pub enum CardinalDirection { North, East, South, West }
 #[derive(Clone, Copy, EnumIter, FromPrimitive, PartialEq)]
 pub enum TetrominoVariant { I, J, L, O, S, T, Z }
 
+impl TetrominoVariant {
+    pub fn from_char(char: char) -> Option<Self> {
+        match char.to_ascii_uppercase() {
+            'I' => Some(TetrominoVariant::I),
+            'J' => Some(TetrominoVariant::J),
+            'L' => Some(TetrominoVariant::L),
+            'O' => Some(TetrominoVariant::O),
+            'S' => Some(TetrominoVariant::S),
+            'T' => Some(TetrominoVariant::T),
+            'Z' => Some(TetrominoVariant::Z),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct Tetromino {
     pub shape: Shape,
@@ -28,52 +43,60 @@ impl Tetromino {
                 shape: vec![(3, 18), (4, 18), (5, 18), (6, 18)],
                 center: (4, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(51) } else { Color::Cyan },
+                color: config::theme().piece_color(variant),
                 variant,
             },
             TetrominoVariant::J => Tetromino {
                 shape: vec![(4, 19), (4, 18), (5, 18), (6, 18)],
                 center: (5, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(33) } else { Color::Blue },
+                color: config::theme().piece_color(variant),
                 variant,
             },
             TetrominoVariant::L => Tetromino {
                 shape: vec![(4, 18), (5, 18), (6, 18), (6, 19)],
                 center: (5, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(202) } else { Color::White },
+                color: config::theme().piece_color(variant),
                 variant,
             },
             TetrominoVariant::O => Tetromino {
                 shape: vec![(4, 18), (4, 19), (5, 18), (5, 19)],
                 center: (4, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(226) } else { Color::Yellow },
+                color: config::theme().piece_color(variant),
                 variant,
             },
             TetrominoVariant::S => Tetromino {
                 shape: vec![(4, 18), (5, 18), (5, 19), (6, 19)],
                 center: (5, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(40) } else { Color::Green },
+                color: config::theme().piece_color(variant),
                 variant,
             },
             TetrominoVariant::T => Tetromino {
                 shape: vec![(4, 18), (5, 18), (5, 19), (6, 18)],
                 center: (5, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(165) } else { Color::Magenta },
+                color: config::theme().piece_color(variant),
                 variant,
             },
             TetrominoVariant::Z => Tetromino {
                 shape: vec![(4, 19), (5, 19), (5, 18), (6, 18)],
                 center: (5, 18),
                 direction: CardinalDirection::North,
-                color: if *config::USE_XTERM_256_COLORS { Color::AnsiValue(196) } else { Color::Red },
+                color: config::theme().piece_color(variant),
                 variant,
             },
         }
     }
+    pub fn translated(mut self, offset: Dimension) -> Self {
+        for position in self.shape.iter_mut() {
+            position.0 += offset.0;
+            position.1 += offset.1;
+        }
+        self.center.0 += offset.0;
+        self.center.1 += offset.1;
+        self
+    }
 }
-
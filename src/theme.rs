@@ -0,0 +1,94 @@
+use crossterm::style::{Color, ContentStyle};
+
+use crate::{config, tetromino::TetrominoVariant};
+
+#[derive(Clone)]
+pub struct Theme {
+    // In the order of TetrominoVariant
+    pub pieces: [Color; 7],
+    pub garbage: Color,
+    pub border: Color,
+    // Color::Reset for the terminal's own
+    pub background: Color,
+    pub text: ContentStyle,
+}
+
+impl Theme {
+    pub fn piece_color(&self, variant: TetrominoVariant) -> Color {
+        self.pieces[variant as usize]
+    }
+}
+
+// The guideline colors, which fall back to the 16 standard colors without xterm 256 color support
+fn guideline() -> Theme {
+    let (pieces, garbage) = if config::settings().use_xterm_256_colors {
+        ([51, 33, 202, 226, 40, 165, 196].map(Color::AnsiValue), Color::AnsiValue(245))
+    } else {
+        ([Color::Cyan, Color::Blue, Color::White, Color::Yellow, Color::Green, Color::Magenta, Color::Red], Color::Grey)
+    };
+    Theme {
+        pieces,
+        garbage,
+        border: Color::Reset,
+        background: Color::Reset,
+        text: ContentStyle::new(),
+    }
+}
+
+fn pastel() -> Theme {
+    Theme {
+        pieces: [159, 111, 216, 229, 157, 183, 210].map(Color::AnsiValue),
+        garbage: Color::AnsiValue(250),
+        border: Color::AnsiValue(183),
+        background: Color::AnsiValue(236),
+        text: ContentStyle { foreground_color: Some(Color::AnsiValue(189)), ..ContentStyle::new() },
+    }
+}
+
+// Pieces are told apart by shades of grey only
+fn monochrome() -> Theme {
+    Theme {
+        pieces: [255, 246, 250, 253, 248, 243, 240].map(Color::AnsiValue),
+        garbage: Color::AnsiValue(237),
+        border: Color::Reset,
+        background: Color::Reset,
+        text: ContentStyle::new(),
+    }
+}
+
+// Written as #RRGGBB
+pub fn parse_hex_color(value: &str) -> Option<Color> {
+    let hex = value.trim().trim_matches('"').strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::Rgb { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+}
+
+pub fn theme(name: &str) -> Option<Theme> {
+    match name.trim().to_lowercase().as_str() {
+        "guideline"     => Some(guideline()),
+        "pastel"        => Some(pastel()),
+        "monochrome"    => Some(monochrome()),
+        _               => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex_color("#ff8000"), Some(Color::Rgb { r: 255, g: 128, b: 0 }));
+        assert_eq!(parse_hex_color(" \"#00A0FF\" "), Some(Color::Rgb { r: 0, g: 160, b: 255 }));
+    }
+
+    #[test]
+    fn rejects_invalid_hex_colors() {
+        for value in ["ff8000", "#ff800", "#ff80000", "#gg8000", "#ff80é", ""] {
+            assert_eq!(parse_hex_color(value), None, "{}", value);
+        }
+    }
+}